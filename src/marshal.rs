@@ -1,17 +1,34 @@
+use std::collections::HashSet;
 use std::mem::MaybeUninit;
+use std::ptr::NonNull;
 
-use facet_core::{Def, Facet, Field, Shape, StructKind, Type, UserType};
-use facet_reflect::{Partial, Peek, ReflectError, VariantError};
+use facet_core::{
+    Def, Facet, Field, FieldAttribute, PointerType, PtrMut, Shape, StructKind, Type, UserType,
+};
+use facet_reflect::{Partial, Peek, ReflectError};
 
+mod arena;
 mod array;
+mod buffer;
 mod enum_;
+mod error;
+mod flat;
 mod map;
 mod object;
+mod options;
+mod patch;
 mod pointer;
 mod scalar;
 mod set;
 
+pub use arena::{Arena, Borrowed};
+pub use buffer::{V8Buffer, V8BufferElement};
+pub use error::{Error, ErrorKind, JsException, OwnedError, PathSegment, ValuePath};
 pub use object::Constructors;
+pub use options::{
+    Coercion, Int64Repr, MapRepr, MarshalOptions, PropertyAccess, PropertyWrite, StringConversion,
+    TypedArrayConversion, UnknownFields, UnmarshalOptions,
+};
 use pointer::{MarshalPointers, UnmarshalPointers};
 
 struct MarshalState<'mem, 'scope, 'constructors, 'env> {
@@ -23,66 +40,129 @@ struct MarshalState<'mem, 'scope, 'constructors, 'env> {
 
     /// Custom object constructors/prototypes.
     pub constructors: &'constructors mut object::Constructors<'scope, 'env>,
+
+    pub options: MarshalOptions,
+
+    /// The value passed to [`to_v8_owned()`] and its size, from which `Vec`s
+    /// may be moved into V8.
+    pub owned: Option<(PtrMut<'mem>, usize)>,
 }
 
-struct UnmarshalState<'mem, 'scope> {
-    pub pointers: UnmarshalPointers<'mem, 'scope>,
+struct UnmarshalState<'scope> {
+    /// Shared pointers (`Rc`/`Arc`) created for JS objects.
+    pub pointers: UnmarshalPointers<'scope>,
     pub string_conversion_buffer: Box<[MaybeUninit<u8>; 128]>,
+    /// Storage for the targets of references, if unmarshalling borrowed types
+    /// is allowed. The public entry points ensure that the arena outlives the
+    /// value being unmarshalled.
+    pub arena: Option<NonNull<Arena>>,
+    pub options: UnmarshalOptions,
+    /// The JS objects currently being unmarshalled, from the root to the
+    /// current value, for cycle detection. The length is the current depth.
+    pub ancestors: HashSet<v8::Local<'scope, v8::Object>>,
+    /// The number of JS values visited so far.
+    pub nodes: usize,
+    /// Whether the value is in a field with the `flat_array` attribute. Like
+    /// the field on the marshalling side, this applies through options and
+    /// pointers, and is cleared by the first object inside the field.
+    pub flat_array: bool,
 }
 
-#[derive(Debug)]
-pub enum Error<'shape> {
-    Exception,
-    Reflect(ReflectError<'shape>),
-    Variant(VariantError),
-    ClobberedTypeTag(&'shape Shape<'shape>),
-    UnexpectedValue {
+impl<'scope> UnmarshalState<'scope> {
+    fn new(arena: Option<&Arena>, options: UnmarshalOptions) -> Self {
+        UnmarshalState {
+            pointers: UnmarshalPointers::default(),
+            string_conversion_buffer: Box::new([MaybeUninit::uninit(); 128]),
+            arena: arena.map(NonNull::from),
+            options,
+            ancestors: HashSet::new(),
+            nodes: 0,
+            flat_array: false,
+        }
+    }
+
+    /// Start unmarshalling the contents of `object`. Must be paired with
+    /// [`UnmarshalState::leave_object()`] if successful.
+    fn enter_object<'shape>(
+        &mut self,
+        object: v8::Local<'scope, v8::Object>,
         shape: &'shape Shape<'shape>,
-        unexpected: &'static str,
-    },
-    IntOverflow(&'shape Shape<'shape>),
-}
+    ) -> Result<(), Error<'shape>> {
+        self.check_proxy(object, shape)?;
+        if self.ancestors.len() >= self.options.max_depth {
+            return Err(Error::DepthLimitExceeded {
+                shape,
+                max_depth: self.options.max_depth,
+            });
+        }
+        if !self.ancestors.insert(object) {
+            return Err(Error::Cycle(shape));
+        }
+        Ok(())
+    }
 
-impl<'shape> Error<'shape> {
-    #[inline]
-    pub(crate) fn unexpected(shape: &'shape Shape<'shape>, unexpected: &'static str) -> Self {
-        Error::UnexpectedValue { shape, unexpected }
+    /// Refuse to read from Proxy objects if only data properties may be read.
+    fn check_proxy<'shape>(
+        &self,
+        object: v8::Local<'scope, v8::Object>,
+        shape: &'shape Shape<'shape>,
+    ) -> Result<(), Error<'shape>> {
+        if self.options.property_access == PropertyAccess::OwnDataOnly && object.is_proxy() {
+            Err(Error::Proxy(shape))
+        } else {
+            Ok(())
+        }
     }
-}
 
-impl std::fmt::Display for Error<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::Exception => write!(f, "exception during serialization"),
-            Error::Reflect(e) => write!(f, "reflection error: {}", e),
-            Error::Variant(e) => write!(f, "variant error: {}", e),
-            Error::ClobberedTypeTag(shape) => write!(
-                f,
-                "serializing this enum variant would clobber the type tag: {shape}"
-            ),
-            Error::UnexpectedValue { shape, unexpected } => {
-                write!(f, "cannot deserialize {shape} from {unexpected}")
-            }
-            Error::IntOverflow(shape) => {
-                write!(f, "integer overflow while deserializing {shape}")
-            }
+    fn leave_object(&mut self, object: v8::Local<'scope, v8::Object>) {
+        self.ancestors.remove(&object);
+    }
+
+    fn count_node<'shape>(&mut self, shape: &'shape Shape<'shape>) -> Result<(), Error<'shape>> {
+        self.nodes += 1;
+        match self.options.max_nodes {
+            Some(max) if self.nodes > max => Err(Error::TooManyNodes { shape, max }),
+            _ => Ok(()),
         }
     }
-}
 
-impl std::error::Error for Error<'_> {}
+    /// Check the length of a JS array or typed array to be unmarshalled into
+    /// `shape`, which must match exactly for fixed-size arrays.
+    fn check_array_len<'shape>(
+        &self,
+        shape: &'shape Shape<'shape>,
+        len: usize,
+    ) -> Result<(), Error<'shape>> {
+        if let Def::Array(def) = shape.def
+            && def.n != len
+        {
+            return Err(Error::LengthMismatch {
+                shape,
+                expected: def.n,
+                actual: len,
+            });
+        }
+        match self.options.max_array_len {
+            Some(max) if len > max => Err(Error::ArrayTooLong { shape, len, max }),
+            _ => Ok(()),
+        }
+    }
 
-impl<'shape> From<ReflectError<'shape>> for Error<'shape> {
-    #[inline]
-    fn from(e: ReflectError<'shape>) -> Self {
-        Error::Reflect(e)
+    fn check_entries<'shape>(
+        &self,
+        shape: &'shape Shape<'shape>,
+        len: usize,
+    ) -> Result<(), Error<'shape>> {
+        match self.options.max_entries {
+            Some(max) if len > max => Err(Error::TooManyEntries { shape, len, max }),
+            _ => Ok(()),
+        }
     }
-}
 
-impl From<VariantError> for Error<'_> {
-    #[inline]
-    fn from(e: VariantError) -> Self {
-        Error::Variant(e)
+    /// The arena for allocating the targets of references, if any.
+    fn arena<'a>(&self) -> Option<&'a Arena> {
+        // SAFETY: See `UnmarshalState::arena`.
+        self.arena.map(|arena| unsafe { arena.as_ref() })
     }
 }
 
@@ -91,7 +171,7 @@ pub fn to_v8<'facet, 'scope, T: Facet<'facet>>(
     scope: &mut v8::HandleScope<'scope>,
     value: &T,
 ) -> Result<v8::Local<'scope, v8::Value>, Error<'facet>> {
-    to_v8_with_constructors(scope, value, &mut Constructors::default())
+    to_v8_with_options(scope, value, &MarshalOptions::default(), &mut Constructors::default())
 }
 
 /// Convert any Rust value to a V8 JavaScript value, using custom constructors
@@ -101,37 +181,233 @@ pub fn to_v8_with_constructors<'facet, 'scope, 'env, T: Facet<'facet>>(
     value: &T,
     constructors: &mut Constructors<'scope, 'env>,
 ) -> Result<v8::Local<'scope, v8::Value>, Error<'facet>> {
+    to_v8_with_options(scope, value, &MarshalOptions::default(), constructors)
+}
+
+/// Convert any Rust value to a V8 JavaScript value, using custom constructors
+/// for certain types and non-default options.
+pub fn to_v8_with_options<'facet, 'scope, 'env, T: Facet<'facet>>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: &T,
+    options: &MarshalOptions,
+    constructors: &mut Constructors<'scope, 'env>,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'facet>> {
+    let scope = &mut v8::TryCatch::new(scope);
     let mut state = MarshalState {
         null: v8::null(scope),
         pointers: MarshalPointers::default(),
         constructors,
+        options: *options,
+        owned: None,
     };
     let peek = Peek::new(value);
-    marshal_value(peek, scope, &mut state, None)
+    marshal_value(peek, scope, &mut state, None).map_err(|err| err.capture_exception(scope))
+}
+
+/// Convert a Rust value to a V8 JavaScript value, consuming it.
+///
+/// `Vec`s of numbers in fields with the `#[facet(typed_array)]` or
+/// `#[facet(clamped_array)]` attribute are not copied. Instead, their
+/// allocation becomes the backing store of the `ArrayBuffer`, and is freed when
+/// the `ArrayBuffer` is garbage collected. V8 accounts for the memory of
+/// backing stores as external memory of the isolate.
+///
+/// This only applies to `Vec`s stored directly in `value`, including inside
+/// nested structs, enums and options. `Vec`s behind pointers (such as
+/// `Box<Vec<u8>>` or `Rc<Vec<u8>>`) and inside other collections are copied,
+/// like everything else is marshalled by [`to_v8_with_options()`].
+pub fn to_v8_owned<'facet, 'scope, 'env, T: Facet<'facet>>(
+    scope: &mut v8::HandleScope<'scope>,
+    mut value: T,
+    options: &MarshalOptions,
+    constructors: &mut Constructors<'scope, 'env>,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'facet>> {
+    let scope = &mut v8::TryCatch::new(scope);
+    let owned = PtrMut::new(&raw mut value);
+    let mut state = MarshalState {
+        null: v8::null(scope),
+        pointers: MarshalPointers::default(),
+        constructors,
+        options: *options,
+        owned: Some((owned, size_of::<T>())),
+    };
+    // SAFETY: `value` is exclusively owned. The `Peek` only reads from it, and
+    // `Vec`s are taken through `owned`, from which the `Peek` is derived.
+    let peek = unsafe { Peek::unchecked_new(owned.as_const(), T::SHAPE) };
+    marshal_value(peek, scope, &mut state, None).map_err(|err| err.capture_exception(scope))
+}
+
+/// Update an existing JS object in place to reflect a Rust value, keeping the
+/// identity of the object and of nested objects, arrays, `Map`s and `Set`s where
+/// possible.
+///
+/// Properties of fields that are `None` or skipped are deleted. Nested values
+/// that cannot be reused (different JS type, enums, shared pointers, typed
+/// arrays, etc.) are replaced by newly created values.
+///
+/// Fails if `T` does not marshal as an object compatible with `object`.
+pub fn to_v8_into<'facet, 'scope, T: Facet<'facet>>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: &T,
+    object: v8::Local<'scope, v8::Object>,
+) -> Result<(), Error<'facet>> {
+    to_v8_into_with_options(
+        scope,
+        value,
+        object,
+        &MarshalOptions::default(),
+        &mut Constructors::default(),
+    )
+}
+
+/// Update an existing JS object in place to reflect a Rust value, like
+/// [`to_v8_into()`], using custom constructors for newly created values and
+/// non-default options.
+pub fn to_v8_into_with_options<'facet, 'scope, 'env, T: Facet<'facet>>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: &T,
+    object: v8::Local<'scope, v8::Object>,
+    options: &MarshalOptions,
+    constructors: &mut Constructors<'scope, 'env>,
+) -> Result<(), Error<'facet>> {
+    let scope = &mut v8::TryCatch::new(scope);
+    let mut state = MarshalState {
+        null: v8::null(scope),
+        pointers: MarshalPointers::default(),
+        constructors,
+        options: *options,
+        owned: None,
+    };
+    let peek = Peek::new(value);
+    let updated = patch::marshal_into_existing(peek, scope, object, &mut state, None)
+        .map_err(|err| err.capture_exception(scope))?;
+    if !updated {
+        return Err(ReflectError::OperationFailed {
+            shape: T::SHAPE,
+            operation: "value cannot be marshalled into the existing object",
+        }
+        .into());
+    }
+    Ok(())
 }
 
 /// Construct a Rust value from a V8 JavaScript value.
 pub fn from_v8<'facet, 'scope, T: Facet<'facet>>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
+) -> Result<T, Error<'facet>> {
+    from_v8_with_options(scope, value, &UnmarshalOptions::default())
+}
+
+/// Construct a Rust value from a V8 JavaScript value, using non-default
+/// options.
+pub fn from_v8_with_options<'facet, 'scope, T: Facet<'facet>>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    options: &UnmarshalOptions,
 ) -> Result<T, Error<'facet>> {
     let mut partial = Partial::alloc_shape(T::SHAPE)?;
-    from_v8_partial(scope, value, &mut partial)?;
+    unmarshal_root(scope, value, &mut partial, None, options)?;
     let value = partial.build()?.materialize()?;
     Ok(value)
 }
 
+/// Construct a Rust value that borrows from `arena` from a V8 JavaScript value.
+///
+/// The targets of references in the value (`&'a str`, `&'a [T]`, `&'a T`) are
+/// allocated in the arena. Multiple references to the same JS object refer to
+/// the same allocation.
+///
+/// The value is returned in a [`Borrowed`], which borrows the arena, so it
+/// cannot outlive it:
+///
+/// ```compile_fail
+/// # fn f<'s>(scope: &mut v8::HandleScope<'s>, value: v8::Local<'s, v8::Value>) {
+/// use facet_v8::{Arena, from_v8_in};
+///
+/// let name = {
+///     let arena = Arena::new();
+///     let name = from_v8_in::<&str>(&arena, scope, value).unwrap();
+///     *name
+/// }; // `arena` is dropped here while `name` still borrows from it.
+/// println!("{name}");
+/// # }
+/// ```
+pub fn from_v8_in<'a, 'scope, T: Facet<'a> + 'a>(
+    arena: &'a Arena,
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+) -> Result<Borrowed<'a, T>, Error<'a>> {
+    from_v8_in_with_options(arena, scope, value, &UnmarshalOptions::default())
+}
+
+/// Construct a Rust value that borrows from `arena` from a V8 JavaScript
+/// value, using non-default options. See [`from_v8_in()`].
+pub fn from_v8_in_with_options<'a, 'scope, T: Facet<'a> + 'a>(
+    arena: &'a Arena,
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    options: &UnmarshalOptions,
+) -> Result<Borrowed<'a, T>, Error<'a>> {
+    let mut partial = Partial::alloc_shape(T::SHAPE)?;
+    unmarshal_root(scope, value, &mut partial, Some(arena), options)?;
+    let value = partial.build()?.materialize()?;
+    Ok(Borrowed::new(arena, value))
+}
+
+/// Update an existing Rust value with the contents of a V8 JavaScript value.
+///
+/// Only the fields that are present as own properties of the JS object are
+/// overwritten, recursing into nested structs, so `{ volume: 0.5 }` updates
+/// the `volume` field and leaves everything else untouched. Values that are not
+/// structs with named fields (lists, maps, enums, tuple structs, etc.) are
+/// replaced as a whole.
+///
+/// If an error occurs, the fields that were updated before the error keep their
+/// new values.
+pub fn from_v8_into<'facet, 'scope, T: Facet<'facet>>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    target: &mut T,
+) -> Result<(), Error<'facet>> {
+    from_v8_into_with_options(scope, value, target, &UnmarshalOptions::default())
+}
+
+/// Update an existing Rust value with the contents of a V8 JavaScript value,
+/// like [`from_v8_into()`], using non-default options.
+pub fn from_v8_into_with_options<'facet, 'scope, T: Facet<'facet>>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    target: &mut T,
+    options: &UnmarshalOptions,
+) -> Result<(), Error<'facet>> {
+    let scope = &mut v8::TryCatch::new(scope);
+    let mut state = UnmarshalState::new(None, *options);
+    let target = PtrMut::new(target as *mut T as *mut u8);
+    // SAFETY: `target` is a valid `T` that we have exclusive access to.
+    unsafe { patch::patch_value(scope, value, target, T::SHAPE, &mut state) }
+        .map_err(|err| err.capture_exception(scope))
+}
+
 /// Populate an already allocated [`Partial`] with the contents of a V8 value.
 pub fn from_v8_partial<'scope, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &mut Partial<'facet, 'shape>,
 ) -> Result<(), Error<'facet>> {
-    let mut state = UnmarshalState {
-        pointers: UnmarshalPointers::default(),
-        string_conversion_buffer: Box::new([MaybeUninit::uninit(); 128]),
-    };
-    unmarshal_value(scope, value, partial, &mut state)?;
+    unmarshal_root(scope, value, partial, None, &UnmarshalOptions::default())
+}
+
+fn unmarshal_root<'scope, 'facet, 'shape: 'facet>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    partial: &mut Partial<'facet, 'shape>,
+    arena: Option<&Arena>,
+    options: &UnmarshalOptions,
+) -> Result<(), Error<'facet>> {
+    let scope = &mut v8::TryCatch::new(scope);
+    let mut state = UnmarshalState::new(arena, *options);
+    unmarshal_value(scope, value, partial, &mut state).map_err(|err| err.capture_exception(scope))?;
     Ok(())
 }
 
@@ -150,11 +426,7 @@ fn will_marshal_as_object(shape: &Shape) -> bool {
             Type::Sequence(_) => true,
             Type::User(UserType::Enum(enum_type)) => enum_::will_serialize_as_object(enum_type),
             Type::User(UserType::Struct(_)) => true,
-            Type::Pointer(_) => {
-                // TODO: For now, only string pointers are serialized through
-                // pointers, because of limitations and bugs.
-                false
-            }
+            Type::Pointer(PointerType::Reference(vpt)) => will_marshal_as_object((vpt.target)()),
             _ => false,
         },
     }
@@ -169,6 +441,11 @@ fn marshal_value<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     let shape = peek.shape();
 
     if let (Def::Scalar(_), _) | (_, Type::Primitive(_)) = (shape.def, shape.ty) {
+        if buffer::is_v8_buffer(shape)
+            && let Some(result) = buffer::marshal_v8_buffer(peek, scope)
+        {
+            return result;
+        }
         return scalar::scalar_to_v8(peek, scope, state);
     }
 
@@ -180,20 +457,24 @@ fn marshal_value<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     }
 
     if let Def::SmartPointer(_) = shape.def {
-        return pointer::marshal_smart_pointer(
-            peek.into_smart_pointer().unwrap(),
-            scope,
-            state,
-            field,
-        );
+        return pointer::marshal_smart_pointer(peek, scope, state, field);
     }
     if let Type::Pointer(pointer_type) = shape.ty {
-        return pointer::marshal_pointer(peek, pointer_type, scope, state);
+        return pointer::marshal_pointer(peek, pointer_type, scope, state, field);
     }
-    if let Type::User(UserType::Enum(enum_type)) = shape.ty {
-        if !enum_::will_serialize_as_object(enum_type) {
-            return enum_::marshal_enum_unit(peek.into_enum()?, enum_type, scope);
-        }
+    if let Type::User(UserType::Enum(enum_type)) = shape.ty
+        && !enum_::will_serialize_as_object(enum_type)
+    {
+        return enum_::marshal_enum_unit(peek.into_enum()?, enum_type, scope);
+    }
+
+    if let Some(result) = flat::marshal_flat_array(peek, scope, field) {
+        return result;
+    }
+    if state.owned.is_some()
+        && let Some(array) = array::take_typed_array(peek, scope, state, field)
+    {
+        return Ok(array.into());
     }
 
     // At this point, it is guaranteed that the object will be serialized as a
@@ -203,6 +484,25 @@ fn marshal_value<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     Ok(obj.into())
 }
 
+/// Marshal the value of a struct, tuple or enum variant field, applying field
+/// attributes that affect everything inside the field.
+fn marshal_field<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
+    peek: Peek<'mem, 'facet, 'shape>,
+    scope: &mut v8::HandleScope<'scope>,
+    state: &mut MarshalState<'mem, 'scope, '_, '_>,
+    field: &Field,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
+    let options = state.options;
+    state.options = options.for_field(field);
+    let result = marshal_value(peek, scope, state, Some(field));
+    state.options = options;
+    result
+}
+
+fn has_field_attribute(field: &Field, name: &str) -> bool {
+    field.attributes.contains(&FieldAttribute::Arbitrary(name))
+}
+
 fn marshal_into_object<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     peek: Peek<'mem, 'facet, 'shape>,
     scope: &mut v8::HandleScope<'scope>,
@@ -216,19 +516,19 @@ fn marshal_into_object<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     );
 
     match (shape.def, shape.ty) {
-        (Def::Map(_), _) => map::marshal_map_into(peek.into_map()?, scope, object, state),
+        (Def::Map(_), _) => map::marshal_map_into(peek, scope, object, state),
         (Def::Set(_), _) => set::marshal_set_into(peek, scope, object, state),
         (Def::List(_) | Def::Array(_) | Def::Slice(_), _) => {
             array::marshal_list_object(peek, scope, object, state)
         }
         (_, Type::User(UserType::Struct(struct_type))) if struct_type.kind == StructKind::Tuple => {
-            array::marshal_tuple_object(peek.into_tuple()?, scope, object, state)
+            array::marshal_tuple_object(peek, scope, object, state)
         }
         (_, Type::User(UserType::Enum(_))) => {
             enum_::marshal_enum_object_into(peek.into_enum()?, scope, object, state)
         }
         (_, Type::User(UserType::Struct(_))) => {
-            object::marshal_struct(peek.into_struct()?, scope, object, state)
+            object::marshal_struct(peek, scope, object, state)
         }
         _ => Err(ReflectError::OperationFailed {
             shape,
@@ -242,11 +542,15 @@ fn unmarshal_value<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
+    state.count_node(shape)?;
 
     if let (Def::Scalar(_), _) | (_, Type::Primitive(_)) = (shape.def, shape.ty) {
+        if buffer::is_v8_buffer(shape) {
+            return buffer::unmarshal_v8_buffer(scope, value, partial);
+        }
         return scalar::scalar_from_v8(scope, value, partial, state);
     }
 
@@ -266,7 +570,13 @@ fn unmarshal_value<'scope, 'partial, 'facet, 'shape: 'facet>(
         return pointer::unmarshal_pointer(scope, value, partial, state);
     }
     if let Type::User(UserType::Enum(_)) = shape.ty {
-        return enum_::unmarshal_enum(scope, value, partial, state);
+        let Ok(object) = v8::Local::<v8::Object>::try_from(value) else {
+            return enum_::unmarshal_enum(scope, value, partial, state);
+        };
+        state.enter_object(object, shape)?;
+        let result = enum_::unmarshal_enum(scope, value, partial, state);
+        state.leave_object(object);
+        return result;
     }
 
     let object = value
@@ -275,21 +585,42 @@ fn unmarshal_value<'scope, 'partial, 'facet, 'shape: 'facet>(
             shape,
             operation: "expected an object",
         })?;
-    unmarshal_object(scope, object, partial, state)
+    state.enter_object(object, shape)?;
+    let result = unmarshal_object(scope, object, partial, state);
+    state.leave_object(object);
+    result
+}
+
+/// Unmarshal the value of a struct or enum variant field, applying field
+/// attributes that affect everything inside the field.
+fn unmarshal_field<'scope, 'partial, 'facet, 'shape: 'facet>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+    state: &mut UnmarshalState<'scope>,
+    field: &Field,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let (options, flat_array) = (state.options, state.flat_array);
+    state.options = options.for_field(field);
+    state.flat_array = has_field_attribute(field, "flat_array");
+    let result = unmarshal_value(scope, value, partial, state);
+    (state.options, state.flat_array) = (options, flat_array);
+    result
 }
 
 fn unmarshal_object<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Object>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
+    let flat_array = std::mem::take(&mut state.flat_array);
     match (shape.def, shape.ty) {
         (Def::Map(_), _) => map::unmarshal_map(scope, value, partial, state),
         (Def::Set(_), _) => set::unmarshal_set(scope, value, partial, state),
         (Def::List(_) | Def::Array(_) | Def::Slice(_), _) => {
-            array::unmarshal_list_object(scope, value, partial, state)
+            array::unmarshal_list_object(scope, value, partial, state, flat_array)
         }
         (_, Type::User(UserType::Struct(struct_type))) if struct_type.kind == StructKind::Tuple => {
             array::unmarshal_tuple(scope, value, partial, state)
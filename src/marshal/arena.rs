@@ -0,0 +1,254 @@
+use std::alloc::Layout;
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+use facet_core::{DropInPlaceFn, PtrConst, PtrMut, PtrUninit, Shape};
+use facet_reflect::{HeapValue, Partial, ReflectError};
+
+use super::Error;
+
+/// Storage for the targets of references in values unmarshalled with
+/// [`from_v8_in()`](crate::from_v8_in).
+///
+/// JavaScript values do not live in Rust memory, so a `&'a str`, `&'a [T]` or
+/// `&'a T` coming from JS must point somewhere. Everything allocated in the
+/// arena lives until the arena is dropped, and the borrow checker ensures that
+/// the unmarshalled value does not outlive it.
+///
+/// Allocations are dropped in the reverse order in which they were made. A
+/// value only borrows from allocations made before it, so everything it points
+/// to is still alive while it is dropped.
+#[derive(Default)]
+pub struct Arena {
+    allocations: RefCell<Vec<Allocation>>,
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        let allocations = self.allocations.get_mut();
+        while let Some(allocation) = allocations.pop() {
+            drop(allocation);
+        }
+    }
+}
+
+/// A value unmarshalled with [`from_v8_in()`](crate::from_v8_in), which
+/// borrows from an [`Arena`].
+///
+/// The value is only reachable through this wrapper, which borrows the arena,
+/// so neither the value nor anything borrowed from it can outlive the arena.
+pub struct Borrowed<'a, T: 'a> {
+    value: T,
+    _arena: PhantomData<&'a Arena>,
+}
+
+impl<'a, T: 'a> Borrowed<'a, T> {
+    pub(crate) fn new(_arena: &'a Arena, value: T) -> Self {
+        Borrowed {
+            value,
+            _arena: PhantomData,
+        }
+    }
+
+    /// Take the value out of the wrapper. Its references still borrow from
+    /// the arena for `'a`, so it cannot outlive the arena either.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Borrowed<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T> Deref for Borrowed<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+enum Allocation {
+    Str(#[allow(dead_code)] Box<str>),
+    Value(#[allow(dead_code)] Box<dyn Erased>),
+    Slice(#[allow(dead_code)] RawSlice),
+}
+
+/// Type erasure for [`HeapValue`], which is only ever dropped.
+trait Erased {}
+impl<T> Erased for T {}
+
+/// An array of values with a runtime shape.
+///
+/// Values can be unmarshalled in place with [`push_with()`](Self::push_with),
+/// which is how values without a `Partial` of their own (the pointee of an
+/// `Rc<T>`, or the elements of a `[T; N]`) are built before they are moved to
+/// their final place.
+pub(super) struct RawSlice {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    stride: usize,
+    len: usize,
+    capacity: usize,
+    drop_in_place: Option<DropInPlaceFn>,
+}
+
+impl RawSlice {
+    /// Allocate memory for `capacity` values of `shape`.
+    pub(super) fn with_capacity(shape: &Shape, capacity: usize) -> Self {
+        let stride = shape
+            .layout
+            .sized_layout()
+            .expect("slice elements must be sized")
+            .pad_to_align();
+        let layout = Layout::from_size_align(stride.size() * capacity, stride.align())
+            .expect("slice too large");
+
+        let ptr = if layout.size() == 0 {
+            NonNull::new(layout.align() as *mut u8).unwrap()
+        } else {
+            NonNull::new(unsafe { std::alloc::alloc(layout) })
+                .unwrap_or_else(|| std::alloc::handle_alloc_error(layout))
+        };
+        RawSlice {
+            ptr,
+            layout,
+            stride: stride.size(),
+            len: 0,
+            capacity,
+            drop_in_place: shape.vtable.sized().and_then(|vtable| (vtable.drop_in_place)()),
+        }
+    }
+
+    /// Build the next value in place, with `build` populating a `Partial` of
+    /// `shape`, which must be the shape the slice was allocated for.
+    pub(super) fn push_with<'facet, 'shape>(
+        &mut self,
+        shape: &'shape Shape<'shape>,
+        build: impl FnOnce(&mut Partial<'facet, 'shape>) -> Result<(), Error<'shape>>,
+    ) -> Result<(), Error<'shape>> {
+        assert!(self.len < self.capacity, "slice is full");
+        let slot = unsafe { PtrUninit::new(self.ptr.as_ptr().add(self.len * self.stride)) };
+        // The partial does not own the slot, so if building fails, dropping it
+        // only drops what was initialized.
+        let mut partial = Partial::from_ptr(slot, shape);
+        build(&mut partial)?;
+        // The value stays in the slot. The heap value would free it.
+        std::mem::forget(partial.build()?);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Pointer to the first value.
+    pub(super) fn as_ptr(&self) -> PtrConst<'_> {
+        PtrConst::new(self.ptr.as_ptr())
+    }
+
+    /// Mutable pointer to the first value.
+    pub(super) fn as_mut_ptr(&mut self) -> PtrMut<'_> {
+        PtrMut::new(self.ptr.as_ptr())
+    }
+
+    /// Give up ownership of the values, after they were moved out. Only the
+    /// memory is freed when the slice is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The values must have been moved out bitwise, e.g. with
+    /// `Partial::set_shape()`.
+    pub(super) unsafe fn forget_values(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Drop for RawSlice {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(drop_in_place) = self.drop_in_place {
+                for i in 0..self.len {
+                    drop_in_place(PtrMut::new(self.ptr.as_ptr().add(i * self.stride)));
+                }
+            }
+            if self.layout.size() != 0 {
+                std::alloc::dealloc(self.ptr.as_ptr(), self.layout);
+            }
+        }
+    }
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy a string into the arena.
+    pub(crate) fn alloc_str(&self, s: &str) -> &str {
+        let boxed: Box<str> = s.into();
+        // The contents of the box don't move when the box is moved into the
+        // list of allocations.
+        let s = unsafe { &*(&*boxed as *const str) };
+        self.allocations.borrow_mut().push(Allocation::Str(boxed));
+        s
+    }
+
+    /// Move a value into the arena, returning a pointer to it.
+    ///
+    /// # Safety
+    ///
+    /// The value must only borrow from this arena, or from data that outlives
+    /// it.
+    pub(crate) unsafe fn alloc_value(&self, value: HeapValue<'_, '_>) -> PtrConst<'_> {
+        let ptr = value
+            .peek()
+            .data()
+            .thin()
+            .expect("arena values must be sized");
+        let ptr = PtrConst::new(ptr.as_byte_ptr());
+        let value: Box<dyn Erased + '_> = Box::new(value);
+        // SAFETY: The value is only ever dropped, by the arena. Per the
+        // contract of this function, everything it borrows is either part of
+        // an earlier allocation, which is dropped after it, or outlives the
+        // arena.
+        let value: Box<dyn Erased> = unsafe { std::mem::transmute(value) };
+        self.allocations.borrow_mut().push(Allocation::Value(value));
+        ptr
+    }
+
+    /// Clone a list of values into a contiguous slice in the arena, returning
+    /// a pointer to the first element.
+    pub(crate) fn alloc_slice_cloned<'shape>(
+        &self,
+        shape: &'shape Shape<'shape>,
+        items: &[HeapValue<'_, '_>],
+    ) -> Result<PtrConst<'_>, Error<'shape>> {
+        let vtable = shape.vtable.sized().expect("slice elements must be sized");
+        let clone_into = (vtable.clone_into)().ok_or(ReflectError::OperationFailed {
+            shape,
+            operation: "borrowed slice elements must implement Clone",
+        })?;
+        let mut slice = RawSlice::with_capacity(shape, items.len());
+        for item in items {
+            unsafe {
+                clone_into(
+                    item.peek().data().thin().unwrap(),
+                    PtrUninit::new(slice.ptr.as_ptr().add(slice.len * slice.stride)),
+                );
+            }
+            slice.len += 1;
+        }
+
+        let ptr = PtrConst::new(slice.ptr.as_ptr());
+        self.allocations.borrow_mut().push(Allocation::Slice(slice));
+        Ok(ptr)
+    }
+}
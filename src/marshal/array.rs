@@ -1,11 +1,17 @@
-use std::mem::MaybeUninit;
+use std::ffi::c_void;
+use std::mem::{ManuallyDrop, MaybeUninit};
 
-use facet_core::{ConstTypeId, Field, FieldAttribute, Shape};
-use facet_reflect::{Partial, Peek, PeekTuple};
+use facet_core::{ConstTypeId, Def, Field, PtrMut, Shape};
+use facet_reflect::GenericPtr;
+use facet_reflect::ReflectError;
+use facet_reflect::{Partial, Peek, ScalarType};
 
 use crate::marshal::UnmarshalState;
 
-use super::{Error, MarshalState};
+use super::arena::RawSlice;
+use super::buffer::BufferMemory;
+use super::flat::FlatElement;
+use super::{Error, MarshalState, TypedArrayConversion};
 
 /// Populate an array-like JS object from an array-like Rust type.
 ///
@@ -14,29 +20,37 @@ use super::{Error, MarshalState};
 ///
 /// However, when `object` is a typed array (`Uint8Array`, `Float64Array`,
 /// etc.), it is populated with raw data from the Rust type. When the Rust type
-/// is a matching `Vec<T>` or `&[T]`, the data is copied directly into the JS
-/// `ArrayBuffer` backing the array (effectively a `memcpy()`).
+/// is a matching `Vec<T>`, `[T; N]` or slice (including the target of a
+/// `&[T]`), the data is copied directly into the JS `ArrayBuffer` backing the
+/// array (effectively a `memcpy()`).
 pub fn marshal_list_object<'mem, 'facet: 'mem, 'shape: 'mem + 'facet, 'scope>(
     peek: Peek<'mem, 'facet, 'shape>,
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     state: &mut MarshalState<'mem, 'scope, '_, '_>,
 ) -> Result<(), Error<'shape>> {
+    let shape = peek.shape();
     if object.is_array() {
         // The target object is a plain old array; marshal objects the old
         // fashioned way, no shenanigans.
+        let plain = super::object::is_plain_object(shape, state);
         if let Ok(peek_list) = peek.into_list() {
-            marshal_array_object(peek_list.iter(), scope, object, state)
+            marshal_array_object(peek_list.iter(), scope, object, state, plain)
         } else if let Ok(peek_list_like) = peek.into_list_like() {
-            marshal_array_object(peek_list_like.iter(), scope, object, state)
+            marshal_array_object(peek_list_like.iter(), scope, object, state, plain)
         } else {
-            panic!("expected a list or list-like object to populate an array");
+            Err(ReflectError::OperationFailed {
+                shape,
+                operation: "only lists and list-like values can populate an array",
+            }
+            .into())
         }
     } else if object.is_typed_array() {
         // Fast paths for typed arrays.
-        let peek_list_like = peek
-            .into_list_like()
-            .expect("expected a list-like object for typed array");
+        let peek_list_like = peek.into_list_like().map_err(|_| ReflectError::OperationFailed {
+            shape,
+            operation: "only list-like values can populate a typed array",
+        })?;
         let t = peek_list_like.def().t();
 
         if let Ok(array) = v8::Local::<v8::Uint8Array>::try_from(object) {
@@ -48,30 +62,96 @@ pub fn marshal_list_object<'mem, 'facet: 'mem, 'shape: 'mem + 'facet, 'scope>(
         } else if let Ok(array) = v8::Local::<v8::Int16Array>::try_from(object) {
             i16::marshal(scope, array, peek)
         } else if let Ok(array) = v8::Local::<v8::Uint32Array>::try_from(object) {
+            #[cfg(target_pointer_width = "32")]
+            if t.id == ConstTypeId::of::<usize>() {
+                return usize::marshal(scope, array, peek);
+            }
             u32::marshal(scope, array, peek)
         } else if let Ok(array) = v8::Local::<v8::Int32Array>::try_from(object) {
+            #[cfg(target_pointer_width = "32")]
+            if t.id == ConstTypeId::of::<isize>() {
+                return isize::marshal(scope, array, peek);
+            }
             i32::marshal(scope, array, peek)
+        } else if let Ok(array) = v8::Local::<v8::BigUint64Array>::try_from(object) {
+            #[cfg(target_pointer_width = "64")]
+            if t.id == ConstTypeId::of::<usize>() {
+                return usize::marshal(scope, array, peek);
+            }
+            u64::marshal(scope, array, peek)
+        } else if let Ok(array) = v8::Local::<v8::BigInt64Array>::try_from(object) {
+            #[cfg(target_pointer_width = "64")]
+            if t.id == ConstTypeId::of::<isize>() {
+                return isize::marshal(scope, array, peek);
+            }
+            i64::marshal(scope, array, peek)
         } else if let Ok(array) = v8::Local::<v8::Float32Array>::try_from(object) {
             f32::marshal(scope, array, peek)
         } else if let Ok(array) = v8::Local::<v8::Float64Array>::try_from(object) {
             f64::marshal(scope, array, peek)
+        } else if let Ok(array) = v8::Local::<v8::Uint8ClampedArray>::try_from(object) {
+            // Same memory layout as `Uint8Array`.
+            let buffer = array
+                .buffer(scope)
+                .expect("typed array does not have a backing array buffer");
+            u8::marshal_buffer(buffer, peek)
+        } else if is_float16_array(&object) {
+            if t.id != ConstTypeId::of::<f32>() {
+                return Err(Error::unexpected(shape, object.type_repr()));
+            }
+            let buffer = v8::Local::<v8::TypedArray>::try_from(object)
+                .ok()
+                .and_then(|array| array.buffer(scope))
+                .expect("typed array does not have a backing array buffer");
+            let items = peek_list_like
+                .iter()
+                .filter_map(|item| item.get::<f32>().ok().copied());
+            u16::set_data_iter(buffer, items.map(f32_to_f16));
+            Ok(())
         } else {
-            panic!("array buffer type mismatch: {t}");
+            Err(Error::unexpected(shape, object.type_repr()))
         }
     } else {
-        panic!("object constructor did not create an array or typed array");
+        Err(ReflectError::OperationFailed {
+            shape,
+            operation: "object constructor did not create an array or typed array",
+        }
+        .into())
     }
 }
 
+/// Unmarshal a JS array, typed array or buffer into a sequence. `flat_array`
+/// is whether the sequence is in a field with the `flat_array` attribute.
 pub fn unmarshal_list_object<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
+    flat_array: bool,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let item_shape = list_item_shape(partial.shape());
+    let flat_element = item_shape
+        .filter(|_| flat_array)
+        .and_then(|t| Some((t, FlatElement::of(t)?)));
     if let Ok(array) = object.try_into() {
         unmarshal_array_object(scope, array, partial, state)
-    } else if object.is_typed_array() {
+    } else if let Some((t, element)) = flat_element {
+        // Sequences of arrays and structs in `flat_array` fields are read from
+        // flat buffers.
+        super::flat::unmarshal_flat_array(scope, object, partial, t, element, state)
+    } else if let Ok(typed_array) = v8::Local::<v8::TypedArray>::try_from(object) {
+        let t = item_shape;
+        if state.options.typed_arrays == TypedArrayConversion::Reinterpret
+            && !t.is_some_and(|t| typed_array_matches(&object, t))
+        {
+            return unmarshal_bytes(scope, object, partial, state);
+        }
+        state.check_array_len(partial.shape(), typed_array.length())?;
+        // `usize` and `isize` share the typed array of the integer type with
+        // the same width.
+        let is_usize = t.is_some_and(|t| t.id == ConstTypeId::of::<usize>());
+        let is_isize = t.is_some_and(|t| t.id == ConstTypeId::of::<isize>());
+
         // Fast paths for typed arrays.
         if let Ok(array) = v8::Local::<v8::Uint8Array>::try_from(object) {
             u8::unmarshal(scope, array, partial)?;
@@ -82,18 +162,64 @@ pub fn unmarshal_list_object<'scope, 'partial, 'facet, 'shape: 'facet>(
         } else if let Ok(array) = v8::Local::<v8::Int16Array>::try_from(object) {
             i16::unmarshal(scope, array, partial)?;
         } else if let Ok(array) = v8::Local::<v8::Uint32Array>::try_from(object) {
+            #[cfg(target_pointer_width = "32")]
+            if is_usize {
+                usize::unmarshal(scope, array, partial)?;
+                return Ok(partial);
+            }
             u32::unmarshal(scope, array, partial)?;
         } else if let Ok(array) = v8::Local::<v8::Int32Array>::try_from(object) {
+            #[cfg(target_pointer_width = "32")]
+            if is_isize {
+                isize::unmarshal(scope, array, partial)?;
+                return Ok(partial);
+            }
             i32::unmarshal(scope, array, partial)?;
+        } else if let Ok(array) = v8::Local::<v8::BigUint64Array>::try_from(object) {
+            #[cfg(target_pointer_width = "64")]
+            if is_usize {
+                usize::unmarshal(scope, array, partial)?;
+                return Ok(partial);
+            }
+            u64::unmarshal(scope, array, partial)?;
+        } else if let Ok(array) = v8::Local::<v8::BigInt64Array>::try_from(object) {
+            #[cfg(target_pointer_width = "64")]
+            if is_isize {
+                isize::unmarshal(scope, array, partial)?;
+                return Ok(partial);
+            }
+            i64::unmarshal(scope, array, partial)?;
         } else if let Ok(array) = v8::Local::<v8::Float32Array>::try_from(object) {
             f32::unmarshal(scope, array, partial)?;
         } else if let Ok(array) = v8::Local::<v8::Float64Array>::try_from(object) {
             f64::unmarshal(scope, array, partial)?;
+        } else if object.is_uint8_clamped_array() {
+            // Same memory layout as `Uint8Array`.
+            let memory = BufferMemory::of(scope, object.into())
+                .expect("typed array does not have a backing array buffer");
+            u8::copy_to_partial_list(&memory.bytes(), partial)?;
+        } else if is_float16_array(&object) {
+            unmarshal_float16_array(object, partial)?;
         } else {
-            unreachable!("unhandled TypedArray type (did JS gain new ones?)");
+            return Err(Error::UnexpectedValue {
+                shape: partial.shape(),
+                unexpected: object.type_repr(),
+            });
         }
 
         Ok(partial)
+    } else if object.is_array_buffer() || object.is_shared_array_buffer() || object.is_data_view() {
+        // Untyped memory is accepted for bytes, and reinterpreted for other
+        // element types if enabled.
+        let is_bytes = item_shape
+            .is_some_and(|t| t.id == ConstTypeId::of::<u8>() || t.id == ConstTypeId::of::<i8>());
+        if is_bytes || state.options.typed_arrays == TypedArrayConversion::Reinterpret {
+            return unmarshal_bytes(scope, object, partial, state);
+        }
+        Err(Error::UnexpectedValue {
+            shape: partial.shape(),
+            unexpected: object.type_repr(),
+        })
     } else {
         Err(Error::UnexpectedValue {
             shape: partial.shape(),
@@ -102,36 +228,146 @@ pub fn unmarshal_list_object<'scope, 'partial, 'facet, 'shape: 'facet>(
     }
 }
 
+/// Whether the elements of a typed array have the type `t`, so the typed array
+/// can be unmarshalled without reinterpreting its bytes.
+pub fn typed_array_matches(object: &v8::Object, t: &Shape) -> bool {
+    let is_32_bit = cfg!(target_pointer_width = "32");
+    match ScalarType::try_from_shape(t) {
+        Some(ScalarType::U8) => object.is_uint8_array() || object.is_uint8_clamped_array(),
+        Some(ScalarType::I8) => object.is_int8_array(),
+        Some(ScalarType::U16) => object.is_uint16_array(),
+        Some(ScalarType::I16) => object.is_int16_array(),
+        Some(ScalarType::U32) => object.is_uint32_array(),
+        Some(ScalarType::I32) => object.is_int32_array(),
+        Some(ScalarType::U64) => object.is_big_uint64_array(),
+        Some(ScalarType::I64) => object.is_big_int64_array(),
+        Some(ScalarType::USize) if is_32_bit => object.is_uint32_array(),
+        Some(ScalarType::USize) => object.is_big_uint64_array(),
+        Some(ScalarType::ISize) if is_32_bit => object.is_int32_array(),
+        Some(ScalarType::ISize) => object.is_big_int64_array(),
+        Some(ScalarType::F32) => object.is_float32_array() || is_float16_array(object),
+        Some(ScalarType::F64) => object.is_float64_array() || is_float16_array(object),
+        _ => false,
+    }
+}
+
+/// Whether `object` is a `Float16Array`. The `v8` crate has no binding for
+/// them, but they are the only kind of typed array that is not one of the
+/// others.
+pub fn is_float16_array(object: &v8::Object) -> bool {
+    object.is_typed_array()
+        && !(object.is_uint8_array()
+            || object.is_uint8_clamped_array()
+            || object.is_int8_array()
+            || object.is_uint16_array()
+            || object.is_int16_array()
+            || object.is_uint32_array()
+            || object.is_int32_array()
+            || object.is_big_uint64_array()
+            || object.is_big_int64_array()
+            || object.is_float32_array()
+            || object.is_float64_array())
+}
+
+/// Copy the memory of a typed array, `DataView`, `ArrayBuffer` or
+/// `SharedArrayBuffer` into a list of numbers, reinterpreting the bytes as the
+/// element type.
+fn unmarshal_bytes<'scope, 'partial, 'facet, 'shape: 'facet>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let shape = partial.shape();
+    let (Some(memory), Some(t)) = (BufferMemory::of(scope, object.into()), list_item_shape(shape))
+    else {
+        return Err(Error::unexpected(shape, object.type_repr()));
+    };
+
+    macro_rules! reinterpret {
+        ($($type:ty),*) => {
+            $(
+                if t.id == ConstTypeId::of::<$type>() {
+                    // Like `new Float32Array(buffer, byteOffset, length)`, the
+                    // memory must be aligned to the element size.
+                    let operation = if memory.byte_offset % size_of::<$type>() != 0 {
+                        "buffer offset is not a multiple of the element size"
+                    } else if memory.byte_length % size_of::<$type>() != 0 {
+                        "buffer length is not a multiple of the element size"
+                    } else {
+                        state.check_array_len(shape, memory.byte_length / size_of::<$type>())?;
+                        return <$type>::copy_to_partial_list(&memory.bytes(), partial);
+                    };
+                    return Err(ReflectError::OperationFailed { shape, operation }.into());
+                }
+            )*
+        };
+    }
+    reinterpret!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize, f32, f64);
+    Err(Error::unexpected(shape, object.type_repr()))
+}
+
 /// Marshal each item from an iterator and set its value in the array-like
-/// object. `array` can be any object that supports `set_index()`, including
-/// `v8::Array` or any of the typed arrays.
+/// object. `array` can be any object that supports indexed properties,
+/// including `v8::Array` or any of the typed arrays.
 fn marshal_array_object<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     iter: impl Iterator<Item = Peek<'mem, 'facet, 'shape>> + 'mem,
     scope: &mut v8::HandleScope<'scope>,
     array: v8::Local<'scope, v8::Object>,
     state: &mut MarshalState<'mem, 'scope, '_, '_>,
+    plain: bool,
 ) -> Result<(), Error<'shape>> {
     for (i, item) in iter.enumerate() {
-        let item_value = super::marshal_value(item, scope, state, None)?;
-        array
-            .set_index(scope, i as u32, item_value)
-            .ok_or(Error::Exception)?;
+        let item_value =
+            super::marshal_value(item, scope, state, None).map_err(|err| err.at_index(i))?;
+        set_index(scope, array, i as u32, item_value, state, plain)?;
     }
     Ok(())
 }
 
+/// Like [`set_property()`](super::object::set_property), for indices.
+fn set_index<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    array: v8::Local<'scope, v8::Object>,
+    index: u32,
+    value: v8::Local<'scope, v8::Value>,
+    state: &MarshalState,
+    plain: bool,
+) -> Result<(), Error<'shape>> {
+    if super::object::defines_properties(state, plain) {
+        let key = v8::Integer::new_from_unsigned(scope, index);
+        super::object::set_property(scope, array, key.into(), value, state, plain)
+    } else {
+        array
+            .set_index(scope, index, value)
+            .ok_or_else(Error::exception)?;
+        Ok(())
+    }
+}
+
 fn unmarshal_array_object<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Array>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     let len = object.length();
+    state.check_array_len(partial.shape(), len as usize)?;
+    if let Def::Array(_) = partial.shape().def {
+        let shape = partial.shape();
+        return build_fixed_array(partial, len as usize, |i, item| {
+            let value = super::object::get_index(scope, object.into(), i as u32, shape, state)?;
+            super::unmarshal_value(scope, value, item, state).map_err(|err| err.at_index(i))?;
+            Ok(())
+        });
+    }
     let has_default = partial.shape().has_default_attr();
     partial.begin_list()?;
     for i in 0..len {
-        let item = object.get_index(scope, i).ok_or(Error::Exception)?;
-        super::unmarshal_value(scope, item, partial.begin_list_item()?, state)?.end()?;
+        let item = super::object::get_index(scope, object.into(), i, partial.shape(), state)?;
+        super::unmarshal_value(scope, item, partial.begin_list_item()?, state)
+            .map_err(|err| err.at_index(i as usize))?
+            .end()?;
     }
     if has_default {
         partial.fill_unset_fields_from_default()?;
@@ -141,16 +377,16 @@ fn unmarshal_array_object<'scope, 'partial, 'facet, 'shape: 'facet>(
 }
 
 pub fn marshal_tuple_object<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
-    peek: PeekTuple<'mem, 'facet, 'shape>,
+    peek: Peek<'mem, 'facet, 'shape>,
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     state: &mut MarshalState<'mem, 'scope, '_, '_>,
 ) -> Result<(), Error<'shape>> {
-    for (i, (field, field_value)) in peek.fields().enumerate() {
-        let item = super::marshal_value(field_value, scope, state, Some(&field))?;
-        object
-            .set_index(scope, i as u32, item)
-            .ok_or(Error::Exception)?;
+    let plain = super::object::is_plain_object(peek.shape(), state);
+    for (i, (field, field_value)) in peek.into_tuple()?.fields().enumerate() {
+        let item = super::marshal_field(field_value, scope, state, &field)
+            .map_err(|err| err.at_index(i))?;
+        set_index(scope, object, i as u32, item, state, plain)?;
     }
     Ok(())
 }
@@ -159,7 +395,7 @@ pub fn unmarshal_tuple<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     if let Ok(array) = object.try_into() {
         unmarshal_array_object(scope, array, partial, state)
@@ -171,10 +407,152 @@ pub fn unmarshal_tuple<'scope, 'partial, 'facet, 'shape: 'facet>(
     }
 }
 
+/// The item shape of a list-like shape.
+fn list_item_shape<'shape>(shape: &'shape Shape<'shape>) -> Option<&'shape Shape<'shape>> {
+    match shape.def {
+        Def::List(def) => Some(def.t()),
+        Def::Array(def) => Some(def.t()),
+        Def::Slice(def) => Some(def.t()),
+        _ => None,
+    }
+}
+
+/// Build a fixed-size array (`[T; N]`) of `len` items, which must be `N`, with
+/// `build_item` populating the item at each index.
+///
+/// `Partial` only builds the items of lists one by one, so the items are built
+/// in a separate allocation with the layout of the array, and moved into the
+/// array once they are all set.
+pub fn build_fixed_array<'partial, 'facet, 'shape>(
+    partial: &'partial mut Partial<'facet, 'shape>,
+    len: usize,
+    mut build_item: impl FnMut(usize, &mut Partial<'facet, 'shape>) -> Result<(), Error<'shape>>,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let shape = partial.shape();
+    let Def::Array(def) = shape.def else {
+        return Err(ReflectError::OperationFailed {
+            shape,
+            operation: "not a fixed-size array",
+        }
+        .into());
+    };
+    if def.n != len {
+        return Err(Error::LengthMismatch {
+            shape,
+            expected: def.n,
+            actual: len,
+        });
+    }
+    let mut items = RawSlice::with_capacity(def.t(), len);
+    for i in 0..len {
+        items.push_with(def.t(), |item| build_item(i, item))?;
+    }
+    // SAFETY: `N` consecutive items have the layout of `[T; N]`, and they are
+    // moved into the array.
+    unsafe {
+        partial.set_shape(items.as_ptr(), shape)?;
+        items.forget_values();
+    }
+    Ok(partial)
+}
+
+/// The items of an array (`[T; N]`) or slice (`[T]`) of `T`, which are stored
+/// contiguously and can be copied at once.
+fn contiguous_items<'mem, T: 'static>(peek: Peek<'mem, '_, '_>) -> Option<&'mem [T]> {
+    let (ptr, len) = match peek.shape().def {
+        Def::Array(def) if def.t().id == ConstTypeId::of::<T>() => {
+            (peek.data().thin()?.as_byte_ptr(), def.n)
+        }
+        Def::Slice(def) if def.t().id == ConstTypeId::of::<T>() => {
+            let GenericPtr::Wide(wide) = peek.data() else {
+                return None;
+            };
+            (wide.as_byte_ptr(), peek.into_list_like().ok()?.len())
+        }
+        _ => return None,
+    };
+    // SAFETY: The shape says that there are `len` items of type `T` at `ptr`,
+    // which are borrowed for `'mem`.
+    Some(unsafe { std::slice::from_raw_parts(ptr as *const T, len) })
+}
+
+/// Marshal a `Vec` in a typed array field by moving its allocation into V8, for
+/// [`to_v8_owned()`](crate::to_v8_owned).
+///
+/// Returns `None` if the `Vec` is not part of the owned value, in which case it
+/// is copied as usual.
+pub fn take_typed_array<'scope>(
+    peek: Peek<'_, '_, '_>,
+    scope: &mut v8::HandleScope<'scope>,
+    state: &MarshalState,
+    field: Option<&Field>,
+) -> Option<v8::Local<'scope, v8::Object>> {
+    let field = field?;
+    let clamped = super::has_field_attribute(field, "clamped_array");
+    if !clamped && !super::has_field_attribute(field, "typed_array") {
+        return None;
+    }
+    if !super::object::is_plain_object(peek.shape(), state) {
+        return None;
+    }
+    // The `Vec` is moved out through the mutable pointer to the owned value,
+    // not through `peek`, which only grants shared access.
+    let (owned, size) = state.owned?;
+    let offset =
+        (peek.data().thin()?.as_byte_ptr() as usize).checked_sub(owned.as_byte_ptr() as usize)?;
+    if offset >= size {
+        return None;
+    }
+    // SAFETY: The offset is within the owned value.
+    let ptr = unsafe { PtrMut::new(owned.as_mut_byte_ptr().add(offset)) };
+
+    /// Take the `Vec<T>` at `ptr`, if the shape matches and it is not empty.
+    fn take_vec<T: 'static>(peek: Peek<'_, '_, '_>, ptr: PtrMut<'_>) -> Option<Vec<T>> {
+        if peek.shape().id != ConstTypeId::of::<Vec<T>>() {
+            return None;
+        }
+        // SAFETY: The value is exclusively owned by `to_v8_owned()`, and the
+        // shape was checked.
+        let vec = unsafe { ptr.as_mut::<Vec<T>>() };
+        (!vec.is_empty()).then(|| std::mem::take(vec))
+    }
+
+    if clamped {
+        let vec = take_vec::<u8>(peek, ptr)?;
+        let len = vec.len();
+        let buffer = u8::buffer_from_vec(scope, vec);
+        return v8::Uint8ClampedArray::new(scope, buffer, 0, len).map(Into::into);
+    }
+
+    macro_rules! take_typed_array {
+        ($($type:ty),*) => {
+            $(
+                if let Some(vec) = take_vec::<$type>(peek, ptr) {
+                    let buffer = <$type>::buffer_from_vec(scope, vec);
+                    return Some(<$type>::wrap_buffer(scope, buffer).into());
+                }
+            )*
+        };
+    }
+    take_typed_array!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize, f32, f64);
+    None
+}
+
+/// Whether the field has one of the attributes that marshal sequences as typed
+/// arrays (or `ArrayBuffer`s for `flat_array`).
+pub fn is_typed_array_field(field: &Field) -> bool {
+    super::has_field_attribute(field, "typed_array")
+        || super::has_field_attribute(field, "clamped_array")
+        || super::has_field_attribute(field, "float16_array")
+        || super::has_field_attribute(field, "flat_array")
+}
+
 /// Create an array for the given shape.
 ///
-/// If the field has the `array_buffer` attribute, a typed array is created.
-/// Otherwise, a plain JS array is created with the specified length.
+/// If the field has the `typed_array` attribute, a typed array is created, or
+/// a `Uint8ClampedArray` or `Float16Array` for the `clamped_array` and
+/// `float16_array` attributes. Otherwise, a plain JS array is created with the
+/// specified length.
 pub fn create_array_for_shape<'shape, 'scope>(
     scope: &mut v8::HandleScope<'scope>,
     len: usize,
@@ -182,10 +560,29 @@ pub fn create_array_for_shape<'shape, 'scope>(
     field: Option<&Field>,
 ) -> Result<v8::Local<'scope, v8::Object>, Error<'shape>> {
     if let Some(field) = field {
-        if field
-            .attributes
-            .contains(&FieldAttribute::Arbitrary("typed_array"))
-        {
+        if super::has_field_attribute(field, "clamped_array") {
+            if t.id != ConstTypeId::of::<u8>() {
+                return Err(ReflectError::OperationFailed {
+                    shape: t,
+                    operation: "`clamped_array` requires a sequence of `u8`",
+                }
+                .into());
+            }
+            let buffer = v8::ArrayBuffer::new(scope, len);
+            let array = v8::Uint8ClampedArray::new(scope, buffer, 0, len).unwrap();
+            return Ok(array.into());
+        }
+        if super::has_field_attribute(field, "float16_array") {
+            if t.id != ConstTypeId::of::<f32>() {
+                return Err(ReflectError::OperationFailed {
+                    shape: t,
+                    operation: "`float16_array` requires a sequence of `f32`",
+                }
+                .into());
+            }
+            return new_float16_array(scope, len, t);
+        }
+        if super::has_field_attribute(field, "typed_array") {
             return create_arraybuffer_for_shape(scope, len, t);
         }
     }
@@ -193,8 +590,42 @@ pub fn create_array_for_shape<'shape, 'scope>(
     Ok(v8::Array::new(scope, len.try_into().expect("array too large")).into())
 }
 
+/// Create a `Float16Array` of `len` elements with its global constructor,
+/// because the `v8` crate has no binding for them.
+fn new_float16_array<'shape, 'scope>(
+    scope: &mut v8::HandleScope<'scope>,
+    len: usize,
+    t: &'shape Shape<'shape>,
+) -> Result<v8::Local<'scope, v8::Object>, Error<'shape>> {
+    let global = scope.get_current_context().global(scope);
+    let name = v8::String::new(scope, "Float16Array").unwrap();
+    let constructor = global
+        .get(scope, name.into())
+        .ok_or_else(Error::exception)?;
+    let Ok(constructor) = v8::Local::<v8::Function>::try_from(constructor) else {
+        return Err(ReflectError::OperationFailed {
+            shape: t,
+            operation: "`Float16Array` is not supported by this isolate",
+        }
+        .into());
+    };
+    let len = v8::Number::new(scope, len as f64);
+    let array = constructor
+        .new_instance(scope, &[len.into()])
+        .ok_or_else(Error::exception)?;
+    // The global may have been replaced by JS code.
+    if !is_float16_array(&array) {
+        return Err(ReflectError::OperationFailed {
+            shape: t,
+            operation: "global `Float16Array` constructor did not create a `Float16Array`",
+        }
+        .into());
+    }
+    Ok(array)
+}
+
 /// Create a typed array with the appropriate type for the given shape.
-fn create_arraybuffer_for_shape<'shape, 'scope>(
+pub fn create_arraybuffer_for_shape<'shape, 'scope>(
     scope: &mut v8::HandleScope<'scope>,
     len: usize,
     t: &'shape Shape<'shape>,
@@ -211,12 +642,24 @@ fn create_arraybuffer_for_shape<'shape, 'scope>(
         i16::create_typed_array_for_len(scope, len).into()
     } else if t.id == ConstTypeId::of::<i32>() {
         i32::create_typed_array_for_len(scope, len).into()
+    } else if t.id == ConstTypeId::of::<u64>() {
+        u64::create_typed_array_for_len(scope, len).into()
+    } else if t.id == ConstTypeId::of::<i64>() {
+        i64::create_typed_array_for_len(scope, len).into()
+    } else if t.id == ConstTypeId::of::<usize>() {
+        usize::create_typed_array_for_len(scope, len).into()
+    } else if t.id == ConstTypeId::of::<isize>() {
+        isize::create_typed_array_for_len(scope, len).into()
     } else if t.id == ConstTypeId::of::<f32>() {
         f32::create_typed_array_for_len(scope, len).into()
     } else if t.id == ConstTypeId::of::<f64>() {
         f64::create_typed_array_for_len(scope, len).into()
     } else {
-        panic!("unsupported array buffer type: {t}");
+        return Err(ReflectError::OperationFailed {
+            shape: t,
+            operation: "typed arrays of this element type are not supported",
+        }
+        .into());
     };
 
     Ok(buffer)
@@ -233,12 +676,59 @@ trait TypedArrayType: bytemuck::Pod + 'static {
         buffer: v8::Local<'scope, v8::ArrayBuffer>,
     ) -> Self::TypedArray<'scope>;
 
+    /// Create an `ArrayBuffer` backed by the allocation of `vec`, without
+    /// copying.
+    fn buffer_from_vec<'scope>(
+        scope: &mut v8::HandleScope<'scope>,
+        vec: Vec<Self>,
+    ) -> v8::Local<'scope, v8::ArrayBuffer> {
+        unsafe extern "C" fn drop_vec<T>(
+            data: *mut c_void,
+            byte_length: usize,
+            capacity: *mut c_void,
+        ) {
+            // SAFETY: The backing store was created from this `Vec` below.
+            let len = byte_length / size_of::<T>();
+            drop(unsafe { Vec::from_raw_parts(data as *mut T, len, capacity as usize) });
+        }
+
+        // V8 accounts for the byte length of every backing store attached to an
+        // `ArrayBuffer` as external memory of the isolate (through the array
+        // buffer extension that is registered with the heap), which drives
+        // garbage collection. Reporting it again with
+        // `adjust_amount_of_external_allocated_memory()` would count it twice.
+        // Spare capacity of the `Vec` is not accounted for.
+        let mut vec = ManuallyDrop::new(vec);
+        // SAFETY: The allocation is valid until the deleter is called.
+        let backing_store = unsafe {
+            v8::ArrayBuffer::new_backing_store_from_ptr(
+                vec.as_mut_ptr() as *mut c_void,
+                vec.len() * size_of::<Self>(),
+                drop_vec::<Self>,
+                vec.capacity() as *mut c_void,
+            )
+        };
+        v8::ArrayBuffer::with_backing_store(scope, &backing_store.make_shared())
+    }
+
     /// Given a `TypedArray` handle and a `Peek` representing a sequence, copy
     /// the data from the container into the array in the fastest possible way.
     fn marshal<'scope, 'shape>(
         scope: &mut v8::HandleScope<'scope>,
         handle: Self::TypedArray<'scope>,
         peek: Peek<'_, '_, 'shape>,
+    ) -> Result<(), Error<'shape>> {
+        let handle: v8::Local<v8::TypedArray> = handle.into();
+        let buffer = handle
+            .buffer(scope)
+            .expect("typed array does not have a backing array buffer");
+        Self::marshal_buffer(buffer, peek)
+    }
+
+    /// Like [`marshal()`](Self::marshal), given the buffer backing the array.
+    fn marshal_buffer<'shape>(
+        buffer: v8::Local<v8::ArrayBuffer>,
+        peek: Peek<'_, '_, 'shape>,
     ) -> Result<(), Error<'shape>>;
 
     /// Given a `TypedArray` handle and a `Partial` container, copy the data
@@ -249,8 +739,10 @@ trait TypedArrayType: bytemuck::Pod + 'static {
         container: &'partial mut Partial<'facet, 'shape>,
     ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>>;
 
+    /// Copy the bytes of a buffer into a list of `Self`. The length of `bytes`
+    /// must be a multiple of the size of `Self`, but it may be unaligned.
     fn copy_to_partial_list<'partial, 'facet, 'shape>(
-        buffer: v8::Local<v8::ArrayBuffer>,
+        buffer_bytes: &[u8],
         partial: &'partial mut Partial<'facet, 'shape>,
     ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>>
     where
@@ -258,24 +750,16 @@ trait TypedArrayType: bytemuck::Pod + 'static {
         Vec<Self>: facet_core::Facet<'facet>,
         Self: facet_core::Facet<'facet>,
     {
-        let byte_len = buffer.byte_length();
-        let buffer_bytes: &[u8] = unsafe {
-            buffer
-                .data()
-                .map(|ptr| std::slice::from_raw_parts(ptr.as_ptr() as *mut u8, byte_len))
-                .unwrap_or(&[])
-        };
-
         // Fast path for Vec.
         if partial.shape().id == ConstTypeId::of::<Vec<Self>>() {
-            let len = byte_len / size_of::<Self>();
+            let len = buffer_bytes.len() / size_of::<Self>();
             let mut vec = Vec::<MaybeUninit<Self>>::with_capacity(len);
             unsafe {
                 vec.set_len(len);
                 std::ptr::copy_nonoverlapping(
-                    buffer_bytes.as_ptr() as *const Self,
-                    vec.as_mut_ptr() as *mut Self,
-                    len,
+                    buffer_bytes.as_ptr(),
+                    vec.as_mut_ptr() as *mut u8,
+                    len * size_of::<Self>(),
                 );
                 let vec: Vec<Self> = std::mem::transmute(vec);
                 partial.set(vec)?;
@@ -283,8 +767,17 @@ trait TypedArrayType: bytemuck::Pod + 'static {
             }
         }
 
+        let mut chunks = buffer_bytes.chunks_exact(size_of::<Self>());
+        if let Def::Array(_) = partial.shape().def {
+            return build_fixed_array(partial, chunks.len(), |_, item| {
+                let chunk = chunks.next().unwrap();
+                item.set(bytemuck::pod_read_unaligned::<Self>(chunk))?;
+                Ok(())
+            });
+        }
+
         partial.begin_list()?;
-        for chunk in buffer_bytes.chunks_exact(size_of::<Self>()) {
+        for chunk in chunks {
             let item: Self = bytemuck::pod_read_unaligned(chunk);
             partial.push(item)?;
         }
@@ -352,14 +845,10 @@ macro_rules! impl_typed_array_type {
                 .unwrap()
             }
 
-            fn marshal<'scope, 'shape>(
-                scope: &mut v8::HandleScope<'scope>,
-                handle: Self::TypedArray<'scope>,
+            fn marshal_buffer<'shape>(
+                buffer: v8::Local<v8::ArrayBuffer>,
                 peek: Peek<'_, '_, 'shape>,
             ) -> Result<(), Error<'shape>> {
-                let buffer = handle
-                    .buffer(scope)
-                    .expect("typed array does not have a backing array buffer");
                 if let Ok(vec) = peek.get::<Vec<$type>>() {
                     // Fast path for Vec.
                     Self::set_data_slice(buffer, &*vec);
@@ -367,22 +856,35 @@ macro_rules! impl_typed_array_type {
                 // } else if let Ok(boxed) = peek.get::<Box<[$type]>>() {
                 //     // Fast path for boxed slices.
                 //     Self::set_data_slice(buffer, &*boxed);
+                } else if let Some(slice) = contiguous_items::<$type>(peek) {
+                    // Fast path for arrays and slices, including the targets
+                    // of `&[T]`.
+                    Self::set_data_slice(buffer, slice);
                 } else if let Ok(slice) = peek.get::<&[$type]>() {
                     // Fast path for slices.
                     Self::set_data_slice(buffer, slice);
                 // TODO: VecDeque when `facet` supports it.
                 // } else if let Ok(vec_deque) = peek.get::<std::collections::VecDeque<$type>>() {
                 //     // Fast path for VecDeque.
-                //     Self::set_data_from_iter(buffer, vec_deque.iter().copied());
+                //     Self::set_data_iter(buffer, vec_deque.iter().copied());
                 } else {
-                    // Otherwise, we assume it's a list-like object. This is
-                    // also checked further up the call stack, so just unwrap.
-                    let peek_list_like = peek.into_list_like().unwrap();
+                    // Otherwise, it must be a list-like object of `$type`, or
+                    // the typed array was created by a custom constructor for
+                    // a different type.
+                    let shape = peek.shape();
+                    let mismatch = || ReflectError::OperationFailed {
+                        shape,
+                        operation: "typed array does not match the element type",
+                    };
+                    let peek_list_like = peek.into_list_like().map_err(|_| mismatch())?;
+                    if peek_list_like.def().t().id != ConstTypeId::of::<$type>() {
+                        return Err(mismatch().into());
+                    }
                     Self::set_data_iter(
                         buffer,
                         peek_list_like
                             .iter()
-                            .map(|item| *item.get::<$type>().expect("array buffer type mismatch")),
+                            .filter_map(|item| item.get::<$type>().ok().copied()),
                     );
                 }
                 Ok(())
@@ -393,10 +895,9 @@ macro_rules! impl_typed_array_type {
                 handle: Self::TypedArray<'scope>,
                 container: &'partial mut Partial<'facet, 'shape>,
             ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
-                let buffer = handle
-                    .buffer(scope)
+                let memory = BufferMemory::of(scope, handle.into())
                     .expect("typed array does not have a backing array buffer");
-                Self::copy_to_partial_list(buffer, container)?;
+                Self::copy_to_partial_list(&memory.bytes(), container)?;
                 Ok(container)
             }
         }
@@ -409,5 +910,108 @@ impl_typed_array_type!(u16, Uint16Array);
 impl_typed_array_type!(i16, Int16Array);
 impl_typed_array_type!(u32, Uint32Array);
 impl_typed_array_type!(i32, Int32Array);
+impl_typed_array_type!(u64, BigUint64Array);
+impl_typed_array_type!(i64, BigInt64Array);
+#[cfg(target_pointer_width = "64")]
+impl_typed_array_type!(usize, BigUint64Array);
+#[cfg(target_pointer_width = "64")]
+impl_typed_array_type!(isize, BigInt64Array);
+#[cfg(target_pointer_width = "32")]
+impl_typed_array_type!(usize, Uint32Array);
+#[cfg(target_pointer_width = "32")]
+impl_typed_array_type!(isize, Int32Array);
 impl_typed_array_type!(f32, Float32Array);
 impl_typed_array_type!(f64, Float64Array);
+
+/// Rust has no stable `f16` type, so `Float16Array`s are converted to
+/// sequences of `f32` or `f64`.
+fn unmarshal_float16_array<'partial, 'facet, 'shape>(
+    object: v8::Local<v8::Object>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let is_f64 = list_item_shape(partial.shape()).is_some_and(|t| t.id == ConstTypeId::of::<f64>());
+    let array = v8::Local::<v8::ArrayBufferView>::try_from(object)
+        .expect("typed arrays are array buffer views");
+    let mut bytes = vec![0; array.byte_length()];
+    array.copy_contents(&mut bytes);
+
+    let mut values = bytes
+        .chunks_exact(size_of::<u16>())
+        .map(|chunk| f16_to_f32(bytemuck::pod_read_unaligned(chunk)));
+    if let Def::Array(_) = partial.shape().def {
+        return build_fixed_array(partial, values.len(), |_, item| {
+            let value = values.next().unwrap();
+            if is_f64 {
+                item.set(value as f64)?;
+            } else {
+                item.set(value)?;
+            }
+            Ok(())
+        });
+    }
+
+    partial.begin_list()?;
+    for value in values {
+        if is_f64 {
+            partial.push(value as f64)?;
+        } else {
+            partial.push(value)?;
+        }
+    }
+    // Note: `begin_list()` does not push a frame.
+    Ok(partial)
+}
+
+/// Convert the bits of an IEEE 754 half-precision float to `f32`. This is
+/// exact.
+fn f16_to_f32(bits: u16) -> f32 {
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as u32;
+    let magnitude = match exponent {
+        // Zero and subnormals.
+        0 => mantissa as f32 * f32::powi(2.0, -24),
+        0x1f if mantissa == 0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => f32::from_bits(((exponent as u32 + 127 - 15) << 23) | (mantissa << 13)),
+    };
+    if bits & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Convert an `f32` to the bits of an IEEE 754 half-precision float, rounding
+/// to nearest, ties to even, like `Float16Array` does.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity or NaN.
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        // Too large, round to infinity.
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal or zero.
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = 1 << (shift - 1);
+        let rounded = mantissa + half - 1 + ((mantissa >> shift) & 1);
+        return sign | (rounded >> shift) as u16;
+    }
+    // Rounding may carry into the exponent, which yields the correct result,
+    // including infinity.
+    let rounded = mantissa + 0xfff + ((mantissa >> 13) & 1);
+    sign | (((exponent as u32) << 10) + (rounded >> 13)) as u16
+}
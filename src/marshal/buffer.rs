@@ -0,0 +1,347 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use facet_core::{
+    Def, Facet, ScalarAffinity, ScalarDef, Shape, Type, UserType, ValueVTable, value_vtable,
+};
+use facet_reflect::{Partial, Peek, ReflectError};
+
+use super::Error;
+
+/// A view of the memory of a JS `ArrayBuffer`, which is marshalled and
+/// unmarshalled without copying.
+///
+/// A `V8Buffer<T>` can be unmarshalled from a typed array of `T` (or any typed
+/// array for `V8Buffer<u8>` and `V8Buffer<i8>`), a `DataView`, or an
+/// `ArrayBuffer`, and keeps the backing store of the buffer alive. It is
+/// marshalled as a typed array that views the same memory.
+///
+/// JS code can write to the buffer at any time it runs, so the memory is read
+/// by copying it with [`to_vec()`](Self::to_vec) or
+/// [`copy_to_slice()`](Self::copy_to_slice). [`as_slice()`](Self::as_slice)
+/// borrows it without copying, but is unsafe, because the slice must not be
+/// held across calls into JS. Buffers backed by shared memory
+/// (`SharedArrayBuffer`) are rejected, because other threads could write to
+/// them at any time.
+///
+/// For the same reason, a `V8Buffer` cannot be sent to other threads, which
+/// could read it while JS code writes to it:
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<facet_v8::V8Buffer<u8>>();
+/// ```
+pub struct V8Buffer<T> {
+    backing_store: v8::SharedRef<v8::BackingStore>,
+    /// The offset of the view in bytes.
+    byte_offset: usize,
+    /// The number of elements.
+    len: usize,
+    /// The memory is written by JS code on the thread of the isolate, so the
+    /// buffer is `!Send` and `!Sync`.
+    _marker: PhantomData<(T, *const ())>,
+}
+
+impl<T> V8Buffer<T> {
+    /// The number of elements in the view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The offset of the view in bytes from the start of the backing store.
+    pub fn byte_offset(&self) -> usize {
+        self.byte_offset
+    }
+
+    /// The backing store of the `ArrayBuffer`.
+    pub fn backing_store(&self) -> &v8::SharedRef<v8::BackingStore> {
+        &self.backing_store
+    }
+}
+
+impl<T: V8BufferElement> V8Buffer<T> {
+    /// Borrow the memory of a typed array, `DataView` or `ArrayBuffer`.
+    /// `is_typed_array` checks that a typed array has elements of type `T`.
+    fn from_v8<'shape>(
+        scope: &mut v8::HandleScope,
+        value: v8::Local<v8::Value>,
+        shape: &'shape Shape<'shape>,
+        is_typed_array: fn(&v8::Value) -> bool,
+    ) -> Result<Self, Error<'shape>> {
+        if value.is_typed_array() && !is_typed_array(&value) && size_of::<T>() != 1 {
+            return Err(Error::unexpected(shape, value.type_repr()));
+        }
+        let Some(BufferMemory {
+            backing_store,
+            byte_offset,
+            byte_length,
+        }) = BufferMemory::of(scope, value)
+        else {
+            return Err(Error::unexpected(shape, value.type_repr()));
+        };
+
+        let operation = if backing_store.is_shared() {
+            "cannot borrow the memory of a SharedArrayBuffer"
+        } else if byte_length % size_of::<T>() != 0 {
+            "buffer length is not a multiple of the element size"
+        } else if backing_store.data().is_some_and(|data| {
+            !(data.as_ptr() as usize + byte_offset).is_multiple_of(align_of::<T>())
+        }) {
+            "buffer is not aligned for the element type"
+        } else {
+            return Ok(V8Buffer {
+                backing_store,
+                byte_offset,
+                len: byte_length / size_of::<T>(),
+                _marker: PhantomData,
+            });
+        };
+        Err(ReflectError::OperationFailed { shape, operation }.into())
+    }
+}
+
+/// The memory of a typed array, `DataView`, `ArrayBuffer` or
+/// `SharedArrayBuffer`.
+pub struct BufferMemory {
+    pub backing_store: v8::SharedRef<v8::BackingStore>,
+    pub byte_offset: usize,
+    pub byte_length: usize,
+}
+
+impl BufferMemory {
+    /// The memory viewed by `value`, or `None` if it is not a buffer.
+    pub fn of(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<Self> {
+        if let Ok(view) = v8::Local::<v8::ArrayBufferView>::try_from(value) {
+            let buffer = view.buffer(scope)?;
+            Some(BufferMemory {
+                backing_store: buffer.get_backing_store(),
+                byte_offset: view.byte_offset(),
+                byte_length: view.byte_length(),
+            })
+        } else if let Ok(buffer) = v8::Local::<v8::ArrayBuffer>::try_from(value) {
+            Some(BufferMemory {
+                backing_store: buffer.get_backing_store(),
+                byte_offset: 0,
+                byte_length: buffer.byte_length(),
+            })
+        } else if let Ok(buffer) = v8::Local::<v8::SharedArrayBuffer>::try_from(value) {
+            Some(BufferMemory {
+                backing_store: buffer.get_backing_store(),
+                byte_offset: 0,
+                byte_length: buffer.byte_length(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The bytes of the memory. They must be copied before running any JS
+    /// code, which may write to them.
+    ///
+    /// Shared memory can be written by other threads at any time, so it is
+    /// never borrowed. It is copied instead, with an atomic load for each
+    /// byte.
+    pub fn bytes(&self) -> Cow<'_, [u8]> {
+        let Some(data) = self.backing_store.data() else {
+            return Cow::Borrowed(&[]);
+        };
+        // SAFETY: Views are always in bounds of their backing store, which is
+        // kept alive by `self`.
+        let ptr = unsafe { data.as_ptr().cast::<u8>().add(self.byte_offset) };
+        if self.backing_store.is_shared() {
+            let bytes = (0..self.byte_length)
+                // SAFETY: `AtomicU8` has the same layout as `u8`, and other
+                // threads only access shared memory with atomic operations.
+                .map(|i| unsafe { AtomicU8::from_ptr(ptr.add(i)) }.load(Ordering::Relaxed))
+                .collect();
+            Cow::Owned(bytes)
+        } else {
+            // SAFETY: JS code on this thread cannot run while the slice is
+            // borrowed.
+            Cow::Borrowed(unsafe { std::slice::from_raw_parts(ptr, self.byte_length) })
+        }
+    }
+}
+
+impl<T: V8BufferElement> V8Buffer<T> {
+    /// A pointer to the first element. The view was checked to be in bounds and
+    /// aligned when it was created, and the backing store is kept alive by
+    /// `self`.
+    fn as_ptr(&self) -> *const T {
+        match self.backing_store.data() {
+            // SAFETY: The view is in bounds of the backing store.
+            Some(data) => unsafe { data.as_ptr().cast::<u8>().add(self.byte_offset).cast() },
+            None => std::ptr::NonNull::dangling().as_ptr(),
+        }
+    }
+
+    /// Borrow the memory without copying it.
+    ///
+    /// # Safety
+    ///
+    /// No JS code may run while the slice is borrowed, because it could write
+    /// to the buffer.
+    pub unsafe fn as_slice(&self) -> &[T] {
+        // SAFETY: The pointer is valid for `len` elements, and the caller
+        // guarantees that they are not written while borrowed.
+        unsafe { std::slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    /// Copy the elements into a new `Vec`.
+    pub fn to_vec(&self) -> Vec<T> {
+        let mut vec = vec![T::zeroed(); self.len];
+        self.copy_to_slice(&mut vec);
+        vec
+    }
+
+    /// Copy the elements into `dest`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dest` does not have the same length as the buffer.
+    pub fn copy_to_slice(&self, dest: &mut [T]) {
+        assert_eq!(
+            dest.len(),
+            self.len,
+            "destination and buffer have different lengths"
+        );
+        // SAFETY: The buffer is only written by JS code on this thread, which
+        // cannot run during the copy, and `dest` is a Rust slice, so it does
+        // not overlap with the memory of the buffer.
+        unsafe { std::ptr::copy_nonoverlapping(self.as_ptr(), dest.as_mut_ptr(), self.len) };
+    }
+}
+
+impl<T> Clone for V8Buffer<T> {
+    fn clone(&self) -> Self {
+        V8Buffer {
+            backing_store: self.backing_store.clone(),
+            byte_offset: self.byte_offset,
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: V8BufferElement + fmt::Debug> fmt::Debug for V8Buffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.to_vec()).finish()
+    }
+}
+
+unsafe impl<'a, T: V8BufferElement> Facet<'a> for V8Buffer<T> {
+    const VTABLE: &'static ValueVTable = &const {
+        value_vtable!(V8Buffer<T>, |f, _opts| write!(f, "V8Buffer"))
+    };
+
+    const SHAPE: &'static Shape<'static> = &const {
+        Shape::builder_for_sized::<Self>()
+            .type_identifier("V8Buffer")
+            .ty(Type::User(UserType::Opaque))
+            .def(Def::Scalar(
+                ScalarDef::builder()
+                    .affinity(&const { ScalarAffinity::opaque().build() })
+                    .build(),
+            ))
+            .build()
+    };
+}
+
+macro_rules! for_each_buffer_type {
+    ($m:ident) => {
+        $m!(u8, Uint8Array, is_uint8_array);
+        $m!(i8, Int8Array, is_int8_array);
+        $m!(u16, Uint16Array, is_uint16_array);
+        $m!(i16, Int16Array, is_int16_array);
+        $m!(u32, Uint32Array, is_uint32_array);
+        $m!(i32, Int32Array, is_int32_array);
+        $m!(u64, BigUint64Array, is_big_uint64_array);
+        $m!(i64, BigInt64Array, is_big_int64_array);
+        $m!(f32, Float32Array, is_float32_array);
+        $m!(f64, Float64Array, is_float64_array);
+    };
+}
+
+/// The element type of a [`V8Buffer`]: one of the number types that have a
+/// typed array, which are `u8`, `i8`, `u16`, `i16`, `u32`, `i32`, `u64`, `i64`,
+/// `f32` and `f64`. This trait is sealed.
+///
+/// Other types cannot be used, not even ones of the same size:
+///
+/// ```compile_fail
+/// # fn f<'s>(scope: &mut v8::HandleScope<'s>, value: v8::Local<'s, v8::Value>) {
+/// facet_v8::from_v8::<facet_v8::V8Buffer<usize>>(scope, value);
+/// # }
+/// ```
+pub trait V8BufferElement: sealed::Sealed + bytemuck::Pod {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_buffer_element {
+    ($type:ty, $array_type:ident, $is_array:ident) => {
+        impl sealed::Sealed for $type {}
+        impl V8BufferElement for $type {}
+    };
+}
+for_each_buffer_type!(impl_buffer_element);
+
+/// Whether `shape` is a [`V8Buffer`] of any element type. This is cheaper than
+/// comparing against the shape of each `V8Buffer<T>`.
+pub fn is_v8_buffer(shape: &Shape) -> bool {
+    matches!(shape.ty, Type::User(UserType::Opaque)) && shape.type_identifier == "V8Buffer"
+}
+
+/// Marshal a [`V8Buffer`] as a typed array viewing the same memory. Returns
+/// `None` if `peek` is not a `V8Buffer`.
+pub fn marshal_v8_buffer<'scope, 'shape>(
+    peek: Peek<'_, '_, 'shape>,
+    scope: &mut v8::HandleScope<'scope>,
+) -> Option<Result<v8::Local<'scope, v8::Value>, Error<'shape>>> {
+    macro_rules! marshal {
+        ($type:ty, $array_type:ident, $is_array:ident) => {
+            if let Ok(buffer) = peek.get::<V8Buffer<$type>>() {
+                let array_buffer =
+                    v8::ArrayBuffer::with_backing_store(scope, &buffer.backing_store);
+                let array =
+                    v8::$array_type::new(scope, array_buffer, buffer.byte_offset, buffer.len)
+                        .ok_or_else(Error::exception);
+                return Some(array.map(Into::into));
+            }
+        };
+    }
+    for_each_buffer_type!(marshal);
+    None
+}
+
+/// Unmarshal a [`V8Buffer`] from a typed array, `DataView` or `ArrayBuffer`.
+/// `partial` must be a `V8Buffer` (see [`is_v8_buffer()`]).
+pub fn unmarshal_v8_buffer<'scope, 'partial, 'facet, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let shape = partial.shape();
+    macro_rules! unmarshal {
+        ($type:ty, $array_type:ident, $is_array:ident) => {
+            if shape.id == V8Buffer::<$type>::SHAPE.id {
+                let buffer = V8Buffer::<$type>::from_v8(scope, value, shape, v8::Value::$is_array)?;
+                return partial.set(buffer).map_err(Into::into);
+            }
+        };
+    }
+    for_each_buffer_type!(unmarshal);
+    // Only reachable for a type that merely looks like a `V8Buffer`.
+    Err(ReflectError::OperationFailed {
+        shape,
+        operation: "unsupported V8Buffer element type",
+    }
+    .into())
+}
@@ -1,4 +1,4 @@
-use facet_core::{EnumType, Shape, StructKind};
+use facet_core::{EnumType, Field, Shape, StructKind};
 use facet_reflect::{HasFields as _, Partial, PeekEnum, ReflectError};
 
 use super::{Error, MarshalState, UnmarshalState};
@@ -129,6 +129,7 @@ pub fn marshal_enum_object_into<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     let active_variant = peek.active_variant()?;
 
     let tag = serialize_enum_tag(enum_behavior.js_enum_repr, active_variant, scope);
+    let plain = super::object::is_plain_object(shape, state);
 
     // Setting the tag field up front to ensure that V8 uses the optimal
     // metaclass chain.
@@ -137,10 +138,8 @@ pub fn marshal_enum_object_into<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
         enum_behavior.js_enum_tag.as_bytes(),
         v8::NewStringType::Internalized,
     )
-    .ok_or(Error::Exception)?;
-    object
-        .set(scope, tag_field.into(), tag)
-        .ok_or(Error::Exception)?;
+    .ok_or_else(Error::exception)?;
+    super::object::set_property(scope, object, tag_field.into(), tag, state, plain)?;
 
     for (field, field_value) in peek.fields_for_serialize() {
         let field_name = field.name;
@@ -153,11 +152,10 @@ pub fn marshal_enum_object_into<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
             field_name.as_bytes(),
             v8::NewStringType::Internalized,
         )
-        .ok_or(Error::Exception)?;
-        let field_value = super::marshal_value(field_value, scope, state, Some(&field))?;
-        object
-            .set(scope, field_name.into(), field_value)
-            .ok_or(Error::Exception)?;
+        .ok_or_else(Error::exception)?;
+        let field_value = super::marshal_field(field_value, scope, state, &field)
+            .map_err(|err| err.at_field(field.name))?;
+        super::object::set_property(scope, object, field_name.into(), field_value, state, plain)?;
     }
 
     Ok(())
@@ -167,7 +165,7 @@ pub fn unmarshal_enum<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &'partial mut facet_reflect::Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     if let Ok(object) = value.try_into() {
         unmarshal_enum_from_object(scope, object, partial, state)
@@ -183,7 +181,7 @@ fn unmarshal_enum_from_object<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     partial: &'partial mut facet_reflect::Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
     // TODO: Cache this.
@@ -197,13 +195,14 @@ fn unmarshal_enum_from_object<'scope, 'partial, 'facet, 'shape: 'facet>(
     )
     .expect("failed to create enum tag string");
 
-    let Some(tag) = object.get(scope, tag_field.into()) else {
+    let tag = super::object::get_property(scope, object, tag_field.into(), shape, state)?;
+    if tag.is_undefined() {
         return Err(ReflectError::OperationFailed {
             shape,
             operation: "enum object must have a tag field",
         }
         .into());
-    };
+    }
 
     let partial = unmarshal_enum_begin_with_tag(scope, tag, partial, state)?;
 
@@ -218,11 +217,10 @@ fn unmarshal_enum_from_object<'scope, 'partial, 'facet, 'shape: 'facet>(
                 key_conversion: v8::KeyConversionMode::KeepNumbers,
             },
         )
-        .ok_or(Error::Exception)?;
+        .ok_or_else(Error::exception)?;
 
     for i in 0..property_names.length() {
-        let key = property_names.get_index(scope, i).ok_or(Error::Exception)?;
-        let value = object.get(scope, key).ok_or(Error::Exception)?;
+        let key = property_names.get_index(scope, i).ok_or_else(Error::exception)?;
 
         if let Ok(tuple_variant_index) = v8::Local::<v8::Integer>::try_from(key) {
             let tuple_variant_index: usize = tuple_variant_index.value().try_into().map_err(|_| {
@@ -231,13 +229,12 @@ fn unmarshal_enum_from_object<'scope, 'partial, 'facet, 'shape: 'facet>(
                     operation: "enum object has a numeric key that is not a valid tuple variant index",
                 }
             })?;
-            super::unmarshal_value(
-                scope,
-                value,
-                partial.begin_nth_enum_field(tuple_variant_index)?,
-                state,
-            )?
-            .end()?;
+            let field = variant_field(partial, tuple_variant_index)?;
+            let value = super::object::get_property(scope, object, key, shape, state)?;
+            let field_partial = partial.begin_nth_enum_field(tuple_variant_index)?;
+            super::unmarshal_field(scope, value, field_partial, state, field)
+                .map_err(|err| err.at_index(tuple_variant_index))?
+                .end()?;
         } else if let Ok(field_name) = v8::Local::<v8::String>::try_from(key) {
             let field_name =
                 field_name.to_rust_cow_lossy(scope, &mut state.string_conversion_buffer);
@@ -246,10 +243,14 @@ fn unmarshal_enum_from_object<'scope, 'partial, 'facet, 'shape: 'facet>(
                 continue;
             }
             let Some(field_index) = partial.field_index(&field_name) else {
-                // Just skip unknown fields.
+                super::object::check_unknown_field(shape, &field_name, &state.options)?;
                 continue;
             };
-            super::unmarshal_value(scope, value, partial.begin_nth_field(field_index)?, state)?
+            let field = variant_field(partial, field_index)?;
+            let value = super::object::get_property(scope, object, key, shape, state)?;
+            let field_partial = partial.begin_nth_field(field_index)?;
+            super::unmarshal_field(scope, value, field_partial, state, field)
+                .map_err(|err| err.at_field(key.to_rust_string_lossy(scope)))?
                 .end()?;
         } else {
             return Err(ReflectError::OperationFailed {
@@ -264,11 +265,29 @@ fn unmarshal_enum_from_object<'scope, 'partial, 'facet, 'shape: 'facet>(
     Ok(partial)
 }
 
+/// The nth field of the selected variant.
+fn variant_field<'shape>(
+    partial: &Partial<'_, 'shape>,
+    index: usize,
+) -> Result<&'shape Field<'shape>, Error<'shape>> {
+    let shape = partial.shape();
+    partial
+        .selected_variant()
+        .and_then(|variant| variant.data.fields.get(index))
+        .ok_or_else(|| {
+            ReflectError::OperationFailed {
+                shape,
+                operation: "enum variant field index out of bounds",
+            }
+            .into()
+        })
+}
+
 fn unmarshal_enum_begin_with_tag<'scope, 'partial, 'facet, 'shape>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &'partial mut facet_reflect::Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, ReflectError<'shape>> {
     if let Ok(string) = v8::Local::<v8::String>::try_from(value) {
         let variant_name = string.to_rust_cow_lossy(scope, &mut state.string_conversion_buffer);
@@ -277,9 +296,9 @@ fn unmarshal_enum_begin_with_tag<'scope, 'partial, 'facet, 'shape>(
         let variant_repr = integer.value();
         partial.select_variant(variant_repr)
     } else {
-        return Err(ReflectError::OperationFailed {
+        Err(ReflectError::OperationFailed {
             shape: partial.shape(),
             operation: "enum tag must be a string or number",
-        });
+        })
     }
 }
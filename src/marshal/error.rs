@@ -0,0 +1,558 @@
+use facet_core::Shape;
+use facet_reflect::{ReflectError, VariantError};
+
+#[derive(Debug)]
+pub enum Error<'shape> {
+    /// A JavaScript exception was thrown while calling into V8.
+    Exception(Box<JsException>),
+    Reflect(ReflectError<'shape>),
+    Variant(VariantError),
+    ClobberedTypeTag(&'shape Shape<'shape>),
+    UnexpectedValue {
+        shape: &'shape Shape<'shape>,
+        unexpected: &'static str,
+    },
+    IntOverflow(&'shape Shape<'shape>),
+    /// A JS number that is fractional, `NaN` or infinite cannot be converted to
+    /// an integer.
+    NonIntegralNumber {
+        shape: &'shape Shape<'shape>,
+        value: f64,
+    },
+    /// JS objects are nested deeper than the `max_depth` of the
+    /// [`UnmarshalOptions`](crate::UnmarshalOptions).
+    DepthLimitExceeded {
+        shape: &'shape Shape<'shape>,
+        max_depth: usize,
+    },
+    /// An array or typed array is longer than the `max_array_len` of the
+    /// [`UnmarshalOptions`](crate::UnmarshalOptions).
+    ArrayTooLong {
+        shape: &'shape Shape<'shape>,
+        len: usize,
+        max: usize,
+    },
+    /// The length of an array or typed array does not match the length of a
+    /// fixed-size Rust array (`[T; N]`).
+    LengthMismatch {
+        shape: &'shape Shape<'shape>,
+        expected: usize,
+        actual: usize,
+    },
+    /// A string is longer than the `max_string_len` of the
+    /// [`UnmarshalOptions`](crate::UnmarshalOptions), in UTF-8 bytes.
+    StringTooLong {
+        shape: &'shape Shape<'shape>,
+        len: usize,
+        max: usize,
+    },
+    /// A map or set has more entries than the `max_entries` of the
+    /// [`UnmarshalOptions`](crate::UnmarshalOptions).
+    TooManyEntries {
+        shape: &'shape Shape<'shape>,
+        len: usize,
+        max: usize,
+    },
+    /// The value consists of more JS values than the `max_nodes` of the
+    /// [`UnmarshalOptions`](crate::UnmarshalOptions).
+    TooManyNodes {
+        shape: &'shape Shape<'shape>,
+        max: usize,
+    },
+    /// A property that would be read is an accessor property, and only data
+    /// properties may be read
+    /// ([`PropertyAccess::OwnDataOnly`](crate::PropertyAccess::OwnDataOnly)).
+    AccessorProperty {
+        shape: &'shape Shape<'shape>,
+        property: String,
+    },
+    /// A JS object is a Proxy, which may not be read
+    /// ([`PropertyAccess::OwnDataOnly`](crate::PropertyAccess::OwnDataOnly)).
+    Proxy(&'shape Shape<'shape>),
+    /// A JS object contains itself, and the Rust type cannot represent the
+    /// cycle because no shared pointer (`Rc`/`Arc`) is involved. Cycles are
+    /// represented by weak pointers back to an `Rc<T>` or `Arc<T>`, where `T`
+    /// implements `Default`.
+    Cycle(&'shape Shape<'shape>),
+    /// A JS object has a property that does not correspond to a field, and
+    /// unknown fields are denied by the [`UnmarshalOptions`](crate::UnmarshalOptions).
+    UnknownField {
+        shape: &'shape Shape<'shape>,
+        field: String,
+    },
+    /// An error that occurred somewhere inside the value being marshalled or
+    /// unmarshalled. Use [`Error::without_path()`] to get the underlying
+    /// error.
+    AtPath {
+        path: ValuePath,
+        error: Box<Error<'shape>>,
+    },
+}
+
+/// The kind of an [`Error`] or [`OwnedError`], without any details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    Exception,
+    Reflect,
+    Variant,
+    ClobberedTypeTag,
+    UnexpectedValue,
+    IntOverflow,
+    NonIntegralNumber,
+    DepthLimitExceeded,
+    ArrayTooLong,
+    LengthMismatch,
+    StringTooLong,
+    TooManyEntries,
+    TooManyNodes,
+    AccessorProperty,
+    Proxy,
+    Cycle,
+    UnknownField,
+}
+
+/// An owned version of [`Error`] that does not borrow any shapes or V8
+/// handles, so it can be stored, sent across threads, or put into error types
+/// like `anyhow::Error`.
+///
+/// The thrown value of an exception is not retained, only its message and
+/// stack trace.
+#[derive(Debug, Clone)]
+pub struct OwnedError {
+    kind: ErrorKind,
+    message: String,
+    shape: Option<String>,
+    path: Option<ValuePath>,
+    stack_trace: Option<String>,
+}
+
+impl OwnedError {
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The error message, without the location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The name of the type that was being marshalled or unmarshalled, if
+    /// known.
+    pub fn shape(&self) -> Option<&str> {
+        self.shape.as_deref()
+    }
+
+    /// The location of the error inside the value, if it did not occur at the
+    /// top level.
+    pub fn path(&self) -> Option<&ValuePath> {
+        self.path.as_ref()
+    }
+
+    /// The stack trace of the JS exception, if any.
+    pub fn stack_trace(&self) -> Option<&str> {
+        self.stack_trace.as_deref()
+    }
+}
+
+impl std::fmt::Display for OwnedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{} at {path}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for OwnedError {}
+
+impl From<Error<'_>> for OwnedError {
+    #[inline]
+    fn from(error: Error<'_>) -> Self {
+        error.into_owned()
+    }
+}
+
+/// A JavaScript exception caught while marshalling or unmarshalling.
+///
+/// The exception is not propagated to any `v8::TryCatch` of the caller.
+#[derive(Default)]
+pub struct JsException {
+    /// The exception message, as reported by V8.
+    pub message: Option<String>,
+    /// The stack trace, if the thrown value was an `Error` object.
+    pub stack_trace: Option<String>,
+    /// The thrown value.
+    pub value: Option<v8::Global<v8::Value>>,
+}
+
+impl std::fmt::Debug for JsException {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsException")
+            .field("message", &self.message)
+            .field("stack_trace", &self.stack_trace)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The location of a value inside another value, such as
+/// `$.items[3].owner.id`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValuePath {
+    // Stored in reverse order, because paths are built while unwinding.
+    reversed: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A struct field or enum variant field.
+    Field(String),
+    /// An array, tuple, or set element.
+    Index(usize),
+    /// The key of the nth entry of a map.
+    MapKey(usize),
+    /// The value of the nth entry of a map.
+    MapValue(usize),
+}
+
+impl ValuePath {
+    /// The segments of the path, from the outermost value inwards.
+    pub fn segments(&self) -> impl DoubleEndedIterator<Item = &PathSegment> {
+        self.reversed.iter().rev()
+    }
+}
+
+impl std::fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("$")?;
+        for segment in self.segments() {
+            match segment {
+                PathSegment::Field(name) => write!(f, ".{name}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+                PathSegment::MapKey(index) => write!(f, "<key {index}>")?,
+                PathSegment::MapValue(index) => write!(f, "<value {index}>")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'shape> Error<'shape> {
+    /// An exception was thrown. The details are filled in by
+    /// [`Error::capture_exception()`] at the top level.
+    #[inline]
+    pub(crate) fn exception() -> Self {
+        Error::Exception(Box::default())
+    }
+
+    #[inline]
+    pub(crate) fn unexpected(shape: &'shape Shape<'shape>, unexpected: &'static str) -> Self {
+        Error::UnexpectedValue { shape, unexpected }
+    }
+
+    /// Record that the error occurred inside `segment`.
+    pub(crate) fn at(self, segment: PathSegment) -> Self {
+        match self {
+            Error::AtPath { mut path, error } => {
+                path.reversed.push(segment);
+                Error::AtPath { path, error }
+            }
+            error => Error::AtPath {
+                path: ValuePath {
+                    reversed: vec![segment],
+                },
+                error: Box::new(error),
+            },
+        }
+    }
+
+    #[inline]
+    pub(crate) fn at_field(self, name: impl Into<String>) -> Self {
+        self.at(PathSegment::Field(name.into()))
+    }
+
+    #[inline]
+    pub(crate) fn at_index(self, index: usize) -> Self {
+        self.at(PathSegment::Index(index))
+    }
+
+    /// The location of the error inside the value, if it did not occur at the
+    /// top level.
+    pub fn path(&self) -> Option<&ValuePath> {
+        match self {
+            Error::AtPath { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The error without its location.
+    ///
+    /// Errors inside the value are wrapped in [`Error::AtPath`], so match on
+    /// this rather than on the error itself, e.g.
+    /// `matches!(err.without_path(), Error::IntOverflow(_))`.
+    pub fn without_path(&self) -> &Error<'shape> {
+        match self {
+            Error::AtPath { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// Fill in the details of the exception caught by `try_catch`, if this is
+    /// an exception error.
+    pub(crate) fn capture_exception(
+        mut self,
+        try_catch: &mut v8::TryCatch<v8::HandleScope>,
+    ) -> Self {
+        let Some(value) = try_catch.exception() else {
+            return self;
+        };
+        let exception = match &mut self {
+            Error::Exception(exception) => exception,
+            Error::AtPath { error, .. } => match &mut **error {
+                Error::Exception(exception) => exception,
+                _ => return self,
+            },
+            _ => return self,
+        };
+
+        exception.message = try_catch
+            .message()
+            .map(|message| message.get(try_catch).to_rust_string_lossy(try_catch));
+        exception.stack_trace = try_catch
+            .stack_trace()
+            .map(|stack_trace| stack_trace.to_rust_string_lossy(try_catch));
+        exception.value = Some(v8::Global::new(try_catch, value));
+        self
+    }
+}
+
+impl<'shape> Error<'shape> {
+    /// The type that was being marshalled or unmarshalled when the error
+    /// occurred, if known.
+    pub fn shape(&self) -> Option<&'shape Shape<'shape>> {
+        match self {
+            Error::ClobberedTypeTag(shape)
+            | Error::UnexpectedValue { shape, .. }
+            | Error::IntOverflow(shape)
+            | Error::NonIntegralNumber { shape, .. }
+            | Error::DepthLimitExceeded { shape, .. }
+            | Error::ArrayTooLong { shape, .. }
+            | Error::LengthMismatch { shape, .. }
+            | Error::StringTooLong { shape, .. }
+            | Error::TooManyEntries { shape, .. }
+            | Error::TooManyNodes { shape, .. }
+            | Error::AccessorProperty { shape, .. }
+            | Error::Proxy(shape)
+            | Error::Cycle(shape)
+            | Error::UnknownField { shape, .. } => Some(*shape),
+            Error::AtPath { error, .. } => error.shape(),
+            Error::Exception(_) | Error::Reflect(_) | Error::Variant(_) => None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Exception(_) => ErrorKind::Exception,
+            Error::Reflect(_) => ErrorKind::Reflect,
+            Error::Variant(_) => ErrorKind::Variant,
+            Error::ClobberedTypeTag(_) => ErrorKind::ClobberedTypeTag,
+            Error::UnexpectedValue { .. } => ErrorKind::UnexpectedValue,
+            Error::IntOverflow(_) => ErrorKind::IntOverflow,
+            Error::NonIntegralNumber { .. } => ErrorKind::NonIntegralNumber,
+            Error::DepthLimitExceeded { .. } => ErrorKind::DepthLimitExceeded,
+            Error::ArrayTooLong { .. } => ErrorKind::ArrayTooLong,
+            Error::LengthMismatch { .. } => ErrorKind::LengthMismatch,
+            Error::StringTooLong { .. } => ErrorKind::StringTooLong,
+            Error::TooManyEntries { .. } => ErrorKind::TooManyEntries,
+            Error::TooManyNodes { .. } => ErrorKind::TooManyNodes,
+            Error::AccessorProperty { .. } => ErrorKind::AccessorProperty,
+            Error::Proxy(_) => ErrorKind::Proxy,
+            Error::Cycle(_) => ErrorKind::Cycle,
+            Error::UnknownField { .. } => ErrorKind::UnknownField,
+            Error::AtPath { error, .. } => error.kind(),
+        }
+    }
+
+    /// Convert this error into an [`OwnedError`], capturing all details as
+    /// strings.
+    pub fn into_owned(self) -> OwnedError {
+        let stack_trace = match self.without_path() {
+            Error::Exception(exception) => exception.stack_trace.clone(),
+            _ => None,
+        };
+        OwnedError {
+            kind: self.kind(),
+            message: self.without_path().to_string(),
+            shape: self.shape().map(|shape| shape.to_string()),
+            stack_trace,
+            path: match self {
+                Error::AtPath { path, .. } => Some(path),
+                _ => None,
+            },
+        }
+    }
+
+    /// Create a JS error object describing this error.
+    ///
+    /// Type mismatches become a `TypeError`, and integer overflows,
+    /// non-integral numbers, array length mismatches and exceeded limits become
+    /// a `RangeError`.
+    /// Exceptions thrown by JS code become an `Error` with the original
+    /// exception as its `cause`.
+    ///
+    /// The error object has the following additional properties:
+    ///
+    /// - `shape`: The name of the Rust type, if known.
+    /// - `path`: The location of the error inside the value (e.g.
+    ///   `"$.items[3].id"`), if not at the top level.
+    /// - `cause`: The thrown value for exceptions, or a description of the
+    ///   error without its location.
+    ///
+    /// Messages too long for a JS string are replaced by a fixed message, and
+    /// such properties are left out.
+    pub fn to_js_error<'s>(&self, scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Value> {
+        let message = v8::String::new(scope, &self.to_string())
+            .or_else(|| v8::String::new(scope, "error message too long"))
+            .unwrap_or_else(|| v8::String::empty(scope));
+        let inner = self.without_path();
+        let error = match inner {
+            Error::IntOverflow(_)
+            | Error::NonIntegralNumber { .. }
+            | Error::DepthLimitExceeded { .. }
+            | Error::ArrayTooLong { .. }
+            | Error::LengthMismatch { .. }
+            | Error::StringTooLong { .. }
+            | Error::TooManyEntries { .. }
+            | Error::TooManyNodes { .. } => {
+                v8::Exception::range_error(scope, message)
+            }
+            Error::Exception(_) => v8::Exception::error(scope, message),
+            _ => v8::Exception::type_error(scope, message),
+        };
+        let Ok(object) = v8::Local::<v8::Object>::try_from(error) else {
+            return error;
+        };
+
+        let shape: Option<v8::Local<v8::Value>> = self
+            .shape()
+            .and_then(|shape| v8::String::new(scope, &shape.to_string()))
+            .map(Into::into);
+        let path: Option<v8::Local<v8::Value>> = self
+            .path()
+            .and_then(|path| v8::String::new(scope, &path.to_string()))
+            .map(Into::into);
+        let cause: Option<v8::Local<v8::Value>> = match inner {
+            Error::Exception(exception) => exception
+                .value
+                .as_ref()
+                .map(|value| v8::Local::new(scope, value)),
+            inner => v8::String::new(scope, &inner.to_string()).map(Into::into),
+        };
+
+        for (name, value) in [("shape", shape), ("path", path), ("cause", cause)] {
+            let Some(value) = value else {
+                continue;
+            };
+            let name = v8::String::new_from_utf8(
+                scope,
+                name.as_bytes(),
+                v8::NewStringType::Internalized,
+            )
+            .unwrap();
+            object.create_data_property(scope, name.into(), value);
+        }
+        error
+    }
+
+    /// Throw this error as a JS exception in `scope`. See
+    /// [`Error::to_js_error()`].
+    pub fn throw(&self, scope: &mut v8::HandleScope) {
+        let error = self.to_js_error(scope);
+        scope.throw_exception(error);
+    }
+}
+
+impl std::fmt::Display for Error<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Exception(exception) => match &exception.message {
+                Some(message) => write!(f, "JavaScript exception: {message}"),
+                None => write!(f, "JavaScript exception"),
+            },
+            Error::Reflect(e) => write!(f, "reflection error: {}", e),
+            Error::Variant(e) => write!(f, "variant error: {}", e),
+            Error::ClobberedTypeTag(shape) => write!(
+                f,
+                "serializing this enum variant would clobber the type tag: {shape}"
+            ),
+            Error::UnexpectedValue { shape, unexpected } => {
+                write!(f, "cannot deserialize {shape} from {unexpected}")
+            }
+            Error::IntOverflow(shape) => {
+                write!(f, "integer overflow while converting {shape}")
+            }
+            Error::NonIntegralNumber { shape, value } => {
+                write!(f, "cannot deserialize {shape} from non-integral number {value}")
+            }
+            Error::DepthLimitExceeded { shape, max_depth } => {
+                write!(
+                    f,
+                    "maximum depth of {max_depth} exceeded while deserializing {shape}"
+                )
+            }
+            Error::ArrayTooLong { shape, len, max } => write!(
+                f,
+                "array of length {len} exceeds the maximum of {max} while deserializing {shape}"
+            ),
+            Error::LengthMismatch {
+                shape,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "array of length {actual} cannot be deserialized as {shape} of length {expected}"
+            ),
+            Error::StringTooLong { shape, len, max } => write!(
+                f,
+                "string of {len} bytes exceeds the maximum of {max} while deserializing {shape}"
+            ),
+            Error::TooManyEntries { shape, len, max } => write!(
+                f,
+                "{len} entries exceed the maximum of {max} while deserializing {shape}"
+            ),
+            Error::TooManyNodes { shape, max } => write!(
+                f,
+                "more than {max} values while deserializing {shape}"
+            ),
+            Error::AccessorProperty { shape, property } => write!(
+                f,
+                "property `{property}` is an accessor, not a data property, while deserializing {shape}"
+            ),
+            Error::Proxy(shape) => write!(f, "cannot deserialize {shape} from a Proxy"),
+            Error::Cycle(shape) => write!(
+                f,
+                "cyclic object cannot be deserialized as {shape} (use `Rc` or `Arc` to preserve cycles)"
+            ),
+            Error::UnknownField { shape, field } => {
+                write!(f, "unknown field `{field}` while deserializing {shape}")
+            }
+            Error::AtPath { path, error } => write!(f, "{error} at {path}"),
+        }
+    }
+}
+
+impl std::error::Error for Error<'_> {}
+
+impl<'shape> From<ReflectError<'shape>> for Error<'shape> {
+    #[inline]
+    fn from(e: ReflectError<'shape>) -> Self {
+        Error::Reflect(e)
+    }
+}
+
+impl From<VariantError> for Error<'_> {
+    #[inline]
+    fn from(e: VariantError) -> Self {
+        Error::Variant(e)
+    }
+}
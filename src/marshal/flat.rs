@@ -0,0 +1,242 @@
+use facet_core::{BaseRepr, Def, Field, PtrConst, Shape, StructType, Type, UserType};
+use facet_reflect::{Partial, Peek, ReflectError, ScalarType};
+
+use super::buffer::BufferMemory;
+use super::{Error, TypedArrayConversion, UnmarshalState};
+
+/// The layout of the elements of a sequence that is marshalled as one flat
+/// buffer (`#[facet(flat_array)]`).
+///
+/// Elements are plain data: numbers, arrays of plain data, and `#[repr(C)]`
+/// structs of plain data without padding. Every byte of an element belongs to
+/// a number, and any bytes are a valid element, so elements can be copied to
+/// and from JS memory as they are.
+#[derive(Clone, Copy)]
+pub struct FlatElement<'shape> {
+    /// The size of an element in bytes.
+    size: usize,
+    /// For (nested) arrays of numbers, the type of the numbers and how many
+    /// there are in each element. Such sequences are marshalled as a typed
+    /// array of the numbers, and structs as an `ArrayBuffer`.
+    numbers: Option<(&'shape Shape<'shape>, usize)>,
+}
+
+impl<'shape> FlatElement<'shape> {
+    /// The layout of `shape`, or `None` if it is a number or not plain data.
+    pub fn of(shape: &'shape Shape<'shape>) -> Option<Self> {
+        let size = shape.layout.sized_layout().ok()?.size();
+        if size == 0 || is_number(shape) || !is_plain_data(shape) {
+            return None;
+        }
+        Some(FlatElement {
+            size,
+            numbers: array_numbers(shape),
+        })
+    }
+
+    /// The size of an element in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Whether `shape` is a number with a typed array type.
+fn is_number(shape: &Shape) -> bool {
+    matches!(
+        ScalarType::try_from_shape(shape),
+        Some(
+            ScalarType::U8
+                | ScalarType::U16
+                | ScalarType::U32
+                | ScalarType::U64
+                | ScalarType::USize
+                | ScalarType::I8
+                | ScalarType::I16
+                | ScalarType::I32
+                | ScalarType::I64
+                | ScalarType::ISize
+                | ScalarType::F32
+                | ScalarType::F64
+        )
+    )
+}
+
+fn is_plain_data(shape: &Shape) -> bool {
+    match (shape.def, shape.ty) {
+        (Def::Array(def), _) => is_plain_data(def.t()),
+        (_, Type::User(UserType::Struct(struct_type))) => is_plain_struct(shape, struct_type),
+        _ => is_number(shape),
+    }
+}
+
+/// Whether the struct has a stable layout without padding, and only fields of
+/// plain data.
+fn is_plain_struct(shape: &Shape, struct_type: StructType) -> bool {
+    if !matches!(struct_type.repr.base, BaseRepr::C | BaseRepr::Transparent) {
+        return false;
+    }
+    // Fields of `#[repr(C)]` structs are laid out in declaration order, so
+    // there is no padding if each field starts where the previous one ended.
+    let mut offset = 0;
+    for field in struct_type.fields {
+        let Ok(layout) = field.shape().layout.sized_layout() else {
+            return false;
+        };
+        if field.offset != offset || !is_plain_data(field.shape()) {
+            return false;
+        }
+        offset += layout.size();
+    }
+    shape
+        .layout
+        .sized_layout()
+        .is_ok_and(|layout| layout.size() == offset)
+}
+
+/// The number type of a (possibly nested) array of numbers, and the number of
+/// numbers in it.
+fn array_numbers<'shape>(shape: &'shape Shape<'shape>) -> Option<(&'shape Shape<'shape>, usize)> {
+    match shape.def {
+        Def::Array(def) => {
+            let (t, n) = array_numbers(def.t())?;
+            Some((t, n * def.n))
+        }
+        _ if is_number(shape) => Some((shape, 1)),
+        _ => None,
+    }
+}
+
+/// Marshal a sequence in a field with the `flat_array` attribute as one typed
+/// array or `ArrayBuffer`, containing the memory of all elements back to back.
+/// Returns `None` if the field does not have the attribute, or `peek` is not a
+/// sequence.
+pub fn marshal_flat_array<'scope, 'shape>(
+    peek: Peek<'_, '_, 'shape>,
+    scope: &mut v8::HandleScope<'scope>,
+    field: Option<&Field>,
+) -> Option<Result<v8::Local<'scope, v8::Value>, Error<'shape>>> {
+    if !field.is_some_and(|field| super::has_field_attribute(field, "flat_array")) {
+        return None;
+    }
+    let list = peek.into_list_like().ok()?;
+    let t = list.def().t();
+    let Some(element) = FlatElement::of(t) else {
+        return Some(Err(ReflectError::OperationFailed {
+            shape: t,
+            operation: "`flat_array` requires elements that are arrays of numbers, or \
+                `#[repr(C)]` structs of numbers without padding",
+        }
+        .into()));
+    };
+
+    let (object, buffer) = match element.numbers {
+        Some((t, n)) => {
+            let array = match super::array::create_arraybuffer_for_shape(scope, list.len() * n, t) {
+                Ok(array) => array,
+                Err(err) => return Some(Err(err)),
+            };
+            let buffer = v8::Local::<v8::TypedArray>::try_from(array)
+                .ok()
+                .and_then(|array| array.buffer(scope))
+                .expect("typed array does not have a backing array buffer");
+            (array, buffer)
+        }
+        None => {
+            let buffer = v8::ArrayBuffer::new(scope, list.len() * element.size);
+            (buffer.into(), buffer)
+        }
+    };
+
+    let buffer_bytes: &mut [u8] = unsafe {
+        buffer
+            .data()
+            .map(|ptr| {
+                std::slice::from_raw_parts_mut(ptr.as_ptr() as *mut u8, buffer.byte_length())
+            })
+            .unwrap_or(&mut [])
+    };
+    for (item, chunk) in list.iter().zip(buffer_bytes.chunks_exact_mut(element.size)) {
+        let ptr = item
+            .data()
+            .thin()
+            .expect("elements must be sized")
+            .as_byte_ptr();
+        // SAFETY: The element is plain data of `element.size` bytes, all of
+        // which are initialized.
+        chunk.copy_from_slice(unsafe { std::slice::from_raw_parts(ptr, element.size) });
+    }
+    Some(Ok(object.into()))
+}
+
+/// The memory of a flat buffer holding a sequence of `shape` in a field with
+/// the `flat_array` attribute, checked to hold a whole number of elements.
+///
+/// Typed arrays must have the element type of the numbers in arrays, unless
+/// [`TypedArrayConversion::Reinterpret`] is enabled. Structs are read from
+/// untyped buffers, and only from typed arrays with `Reinterpret`.
+pub fn flat_array_memory<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    shape: &'shape Shape<'shape>,
+    element: FlatElement<'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<BufferMemory, Error<'shape>> {
+    if object.is_typed_array() {
+        // `Float16Array`s are converted rather than copied, so their memory
+        // cannot be used.
+        let matches = element.numbers.is_some_and(|(t, _)| {
+            super::array::typed_array_matches(&object, t)
+                && !super::array::is_float16_array(&object)
+        });
+        if !matches && state.options.typed_arrays != TypedArrayConversion::Reinterpret {
+            return Err(Error::unexpected(shape, object.type_repr()));
+        }
+    }
+    let Some(memory) = BufferMemory::of(scope, object.into()) else {
+        return Err(Error::unexpected(shape, object.type_repr()));
+    };
+    if memory.byte_length % element.size != 0 {
+        return Err(ReflectError::OperationFailed {
+            shape,
+            operation: "buffer length is not a multiple of the element size",
+        }
+        .into());
+    }
+    state.check_array_len(shape, memory.byte_length / element.size)?;
+    Ok(memory)
+}
+
+/// Unmarshal a sequence of plain data elements in a field with the
+/// `flat_array` attribute from the memory of a typed array, `DataView`,
+/// `ArrayBuffer` or `SharedArrayBuffer`. See [`flat_array_memory()`].
+pub fn unmarshal_flat_array<'scope, 'partial, 'facet, 'shape: 'facet>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+    item_shape: &'shape Shape<'shape>,
+    element: FlatElement<'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let memory = flat_array_memory(scope, object, partial.shape(), element, state)?;
+    let bytes = memory.bytes();
+    let mut chunks = bytes.chunks_exact(element.size);
+    if let Def::Array(_) = partial.shape().def {
+        return super::array::build_fixed_array(partial, chunks.len(), |_, item| {
+            let chunk = chunks.next().unwrap();
+            // SAFETY: As for the items of lists below.
+            unsafe { item.set_shape(PtrConst::new(chunk.as_ptr()), item_shape)? };
+            Ok(())
+        });
+    }
+
+    partial.begin_list()?;
+    for chunk in chunks {
+        partial.begin_list_item()?;
+        // SAFETY: Any bytes are a valid value of plain data, and `set_shape()`
+        // copies them, so the chunk does not need to be aligned.
+        unsafe { partial.set_shape(PtrConst::new(chunk.as_ptr()), item_shape)? };
+        partial.end()?;
+    }
+    // Note: `begin_list()` does not push a frame.
+    Ok(partial)
+}
@@ -1,20 +1,36 @@
-use facet_reflect::{Partial, PeekMap};
+use facet_core::Shape;
+use facet_reflect::{Partial, Peek, ReflectError, ScalarType};
 
-use super::{Error, MarshalState, UnmarshalState};
+use super::{Error, MapRepr, MarshalState, PathSegment, UnmarshalState};
 
 pub fn marshal_map_into<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
-    peek: PeekMap<'mem, 'facet, 'shape>,
+    peek: Peek<'mem, 'facet, 'shape>,
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     state: &mut MarshalState<'mem, 'scope, '_, '_>,
 ) -> Result<(), Error<'shape>> {
-    let map =
-        v8::Local::<v8::Map>::try_from(object).expect("object constructor did not create a map");
-    for (key, value) in peek.iter() {
-        let key_value = super::marshal_value(key, scope, state, None)?;
-        let value_value = super::marshal_value(value, scope, state, None)?;
-        map.set(scope, key_value, value_value)
-            .ok_or(Error::Exception)?;
+    // The object is a plain object if the map is represented as an object, or
+    // if a custom constructor decided so.
+    let map = v8::Local::<v8::Map>::try_from(object).ok();
+    let plain = super::object::is_plain_object(peek.shape(), state);
+    for (i, (key, value)) in peek.into_map()?.iter().enumerate() {
+        let key_value = super::marshal_value(key, scope, state, None)
+            .map_err(|err| err.at(PathSegment::MapKey(i)))?;
+        let value_value = super::marshal_value(value, scope, state, None)
+            .map_err(|err| err.at(PathSegment::MapValue(i)))?;
+        if let Some(map) = map {
+            map.set(scope, key_value, value_value)
+                .ok_or_else(Error::exception)?;
+        } else {
+            if !key_value.is_string() && !key_value.is_number() {
+                return Err(Error::from(ReflectError::OperationFailed {
+                    shape: key.shape(),
+                    operation: "map keys must be strings or numbers when marshalling maps as objects",
+                })
+                .at(PathSegment::MapKey(i)));
+            }
+            super::object::set_property(scope, object, key_value, value_value, state, plain)?;
+        }
     }
     Ok(())
 }
@@ -23,22 +39,99 @@ pub fn unmarshal_map<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut facet_reflect::Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
-    let map = v8::Local::<v8::Map>::try_from(object).map_err(|_| Error::UnexpectedValue {
-        shape,
-        unexpected: object.type_repr(),
-    })?;
+    let map = match v8::Local::<v8::Map>::try_from(object) {
+        Ok(map) => map,
+        Err(_) if state.options.map_repr == MapRepr::Object => {
+            return unmarshal_map_from_object(scope, object, partial, state);
+        }
+        Err(_) => {
+            return Err(Error::UnexpectedValue {
+                shape,
+                unexpected: object.type_repr(),
+            });
+        }
+    };
 
+    state.check_entries(shape, map.size())?;
     partial.begin_map()?;
     let array = map.as_array(scope);
     for i in 0..array.length() / 2 {
-        let key = array.get_index(scope, i * 2).ok_or(Error::Exception)?;
-        let value = array.get_index(scope, i * 2 + 1).ok_or(Error::Exception)?;
-        super::unmarshal_value(scope, key, partial.begin_key()?, state)?.end()?;
-        super::unmarshal_value(scope, value, partial.begin_value()?, state)?.end()?;
+        let key = array.get_index(scope, i * 2).ok_or_else(Error::exception)?;
+        let value = array.get_index(scope, i * 2 + 1).ok_or_else(Error::exception)?;
+        super::unmarshal_value(scope, key, partial.begin_key()?, state)
+            .map_err(|err| err.at(PathSegment::MapKey(i as usize)))?
+            .end()?;
+        super::unmarshal_value(scope, value, partial.begin_value()?, state)
+            .map_err(|err| err.at(PathSegment::MapValue(i as usize)))?
+            .end()?;
     }
     // Note: `begin_map()` does not push a frame.
     Ok(partial)
 }
+
+fn unmarshal_map_from_object<'scope, 'partial, 'facet, 'shape: 'facet>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<&'partial mut facet_reflect::Partial<'facet, 'shape>, Error<'shape>> {
+    let property_names = object
+        .get_own_property_names(
+            scope,
+            v8::GetPropertyNamesArgs {
+                mode: v8::KeyCollectionMode::OwnOnly,
+                property_filter: v8::PropertyFilter::ONLY_ENUMERABLE
+                    | v8::PropertyFilter::SKIP_SYMBOLS,
+                index_filter: v8::IndexFilter::IncludeIndices,
+                // Integer keys are kept as numbers, so they can be unmarshalled
+                // into integer map keys.
+                key_conversion: v8::KeyConversionMode::KeepNumbers,
+            },
+        )
+        .ok_or_else(Error::exception)?;
+    state.check_entries(partial.shape(), property_names.length() as usize)?;
+
+    partial.begin_map()?;
+    for i in 0..property_names.length() {
+        let key = property_names.get_index(scope, i).ok_or_else(Error::exception)?;
+        let value = super::object::get_property(scope, object, key, partial.shape(), state)?;
+        let key_partial = partial.begin_key()?;
+        let key = if key.is_number() && !is_number_shape(key_partial.shape()) {
+            key.to_string(scope).ok_or_else(Error::exception)?.into()
+        } else {
+            key
+        };
+        super::unmarshal_value(scope, key, key_partial, state)
+            .map_err(|err| err.at(PathSegment::MapKey(i as usize)))?
+            .end()?;
+        super::unmarshal_value(scope, value, partial.begin_value()?, state)
+            .map_err(|err| err.at(PathSegment::MapValue(i as usize)))?
+            .end()?;
+    }
+    Ok(partial)
+}
+
+fn is_number_shape(shape: &Shape) -> bool {
+    matches!(
+        ScalarType::try_from_shape(shape),
+        Some(
+            ScalarType::U8
+                | ScalarType::U16
+                | ScalarType::U32
+                | ScalarType::U64
+                | ScalarType::U128
+                | ScalarType::USize
+                | ScalarType::I8
+                | ScalarType::I16
+                | ScalarType::I32
+                | ScalarType::I64
+                | ScalarType::I128
+                | ScalarType::ISize
+                | ScalarType::F32
+                | ScalarType::F64
+        )
+    )
+}
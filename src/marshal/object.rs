@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 
-use super::{Error, MarshalState, UnmarshalState};
-use facet_core::{ConstTypeId, Def, Facet, Field};
-use facet_reflect::{HasFields as _, Partial, Peek, PeekStruct};
+use super::{
+    Error, MapRepr, MarshalState, PropertyAccess, PropertyWrite, UnknownFields, UnmarshalOptions,
+    UnmarshalState,
+};
+use facet_core::{ConstTypeId, Def, Facet, Field, Shape, Type, UserType};
+use facet_reflect::{HasFields as _, Partial, Peek};
 
 /// Customize how to map Rust types to JavaScript objects.
 ///
@@ -15,7 +18,8 @@ use facet_reflect::{HasFields as _, Partial, Peek, PeekStruct};
 /// Object constructors are invoked without arguments, and fields are set
 /// explicitly after the object is created, which also means that for objects
 /// that have getters or setters, the setters will be invoked during
-/// marshalling. Use with caution.
+/// marshalling, unless [`PropertyWrite::Define`](crate::PropertyWrite::Define)
+/// is used. Use with caution.
 ///
 /// Other behaviors:
 ///
@@ -136,7 +140,7 @@ impl<'scope, 'env> Constructor<'scope, 'env> {
             Constructor::Function(func) => {
                 let len = len.map(|l| v8::Integer::new_from_unsigned(scope, l as u32).into());
                 func.new_instance(scope, len.as_slice())
-                    .ok_or(Error::Exception)
+                    .ok_or_else(Error::exception)
             }
             Constructor::ObjectTemplate(template) => {
                 if len.is_some() {
@@ -144,10 +148,10 @@ impl<'scope, 'env> Constructor<'scope, 'env> {
                         "object templates cannot be used to create arrays or tuples; use a constructor instead"
                     );
                 }
-                template.new_instance(scope).ok_or(Error::Exception)
+                template.new_instance(scope).ok_or_else(Error::exception)
             }
             Constructor::Custom(custom_constructor) => {
-                custom_constructor(scope, peek, field).ok_or(Error::Exception)
+                custom_constructor(scope, peek, field).ok_or_else(Error::exception)
             }
         }
     }
@@ -184,7 +188,10 @@ pub fn create_object_for_shape<'mem, 'facet, 'shape, 'scope>(
         }
 
         match shape.def {
-            Def::Map(_) => v8::Map::new(scope).into(),
+            Def::Map(_) => match state.options.map_repr {
+                MapRepr::Map => v8::Map::new(scope).into(),
+                MapRepr::Object => v8::Object::new(scope),
+            },
             Def::Set(_) => v8::Set::new(scope).into(),
             Def::List(_) | Def::Array(_) | Def::Slice(_) => {
                 unreachable!("list-like objects should have been handled earlier")
@@ -199,24 +206,65 @@ pub fn create_object_for_shape<'mem, 'facet, 'shape, 'scope>(
     Ok(constructed)
 }
 
+/// Whether objects of `shape` are plain objects created by `facet-v8`, rather
+/// than by a custom constructor.
+pub fn is_plain_object(shape: &Shape, state: &MarshalState) -> bool {
+    !state.constructors.constructors.contains_key(&shape.id)
+}
+
+/// Whether properties are defined rather than assigned.
+pub fn defines_properties(state: &MarshalState, plain: bool) -> bool {
+    match state.options.property_write {
+        PropertyWrite::Auto => plain,
+        PropertyWrite::Define => true,
+        PropertyWrite::Set => false,
+    }
+}
+
+/// Write a property of an object being marshalled, according to
+/// [`PropertyWrite`]. `plain` is the result of [`is_plain_object()`], or
+/// `false` for existing objects.
+pub fn set_property<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    key: v8::Local<'scope, v8::Value>,
+    value: v8::Local<'scope, v8::Value>,
+    state: &MarshalState,
+    plain: bool,
+) -> Result<(), Error<'shape>> {
+    if defines_properties(state, plain) {
+        let name = match v8::Local::<v8::Name>::try_from(key) {
+            Ok(name) => name,
+            Err(_) => key.to_string(scope).ok_or_else(Error::exception)?.into(),
+        };
+        object
+            .create_data_property(scope, name, value)
+            .ok_or_else(Error::exception)?;
+    } else {
+        object.set(scope, key, value).ok_or_else(Error::exception)?;
+    }
+    Ok(())
+}
+
 pub fn marshal_struct<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
-    peek: PeekStruct<'mem, 'facet, 'shape>,
+    peek: Peek<'mem, 'facet, 'shape>,
     scope: &mut v8::HandleScope<'scope>,
     obj: v8::Local<'scope, v8::Object>,
     state: &mut MarshalState<'mem, 'scope, '_, '_>,
 ) -> Result<(), Error<'shape>> {
-    let fields = peek.fields_for_serialize();
+    let plain = is_plain_object(peek.shape(), state);
+    let fields = peek.into_struct()?.fields_for_serialize();
     for (field, field_value) in fields {
         let field_name = v8::String::new_from_utf8(
             scope,
             field.name.as_bytes(),
             v8::NewStringType::Internalized,
         )
-        .ok_or(Error::Exception)?;
+        .ok_or_else(Error::exception)?;
 
-        let field_value = super::marshal_value(field_value, scope, state, Some(&field))?;
-        obj.set(scope, field_name.into(), field_value)
-            .ok_or(Error::Exception)?;
+        let field_value = super::marshal_field(field_value, scope, state, &field)
+            .map_err(|err| err.at_field(field.name))?;
+        set_property(scope, obj, field_name.into(), field_value, state, plain)?;
     }
     Ok(())
 }
@@ -225,9 +273,45 @@ pub fn unmarshal_struct<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
-    let property_names = object
+    let property_names = own_property_names(scope, object)?;
+    for i in 0..property_names.length() {
+        let key = property_names.get_index(scope, i).ok_or_else(Error::exception)?;
+        let key = v8::Local::<v8::String>::try_from(key)
+            .expect("v8::GetPropertyNames() returned a non-string key");
+        let field_name = key.to_rust_cow_lossy(scope, &mut state.string_conversion_buffer);
+        // Unknown properties are skipped before reading them, so they may be
+        // accessors even with `PropertyAccess::OwnDataOnly`.
+        let Some(field_index) = partial.field_index(&field_name) else {
+            check_unknown_field(partial.shape(), &field_name, &state.options)?;
+            continue;
+        };
+        let value = get_property(scope, object, key.into(), partial.shape(), state)?;
+        let Type::User(UserType::Struct(struct_type)) = partial.shape().ty else {
+            unreachable!("expected a struct");
+        };
+        let field = &struct_type.fields[field_index];
+        let field_partial = partial.begin_nth_field(field_index)?;
+        super::unmarshal_field(scope, value, field_partial, state, field)
+            .map_err(|err| err.at_field(key.to_rust_string_lossy(scope)))?
+            .end()?;
+    }
+
+    if partial.shape().has_default_attr() {
+        partial.fill_unset_fields_from_default()?;
+    }
+
+    Ok(partial)
+}
+
+/// The names of the own properties of `object` that may correspond to fields,
+/// as strings.
+pub fn own_property_names<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+) -> Result<v8::Local<'scope, v8::Array>, Error<'shape>> {
+    object
         .get_property_names(
             scope,
             v8::GetPropertyNamesArgs {
@@ -239,27 +323,88 @@ pub fn unmarshal_struct<'scope, 'partial, 'facet, 'shape: 'facet>(
                 key_conversion: v8::KeyConversionMode::ConvertToString,
             },
         )
-        .ok_or(Error::Exception)?;
+        .ok_or_else(Error::exception)
+}
 
-    for i in 0..property_names.length() {
-        let key = property_names.get_index(scope, i).ok_or(Error::Exception)?;
-        let key = v8::Local::<v8::String>::try_from(key)
-            .expect("v8::GetPropertyNames() returned a non-string key");
-        let field_name = key.to_rust_cow_lossy(scope, &mut state.string_conversion_buffer);
-        let value = object.get(scope, key.into()).ok_or(Error::Exception)?;
-        let Some(field_index) = partial.field_index(&field_name) else {
-            // Just skip unknown fields. The JS side may add any number of
-            // additional fields for all kinds of reasons, including adding them
-            // in a custom constructor.
-            continue;
-        };
-        super::unmarshal_value(scope, value, partial.begin_nth_field(field_index)?, state)?
-            .end()?;
+/// Handle a property of a JS object that does not correspond to a field of
+/// `shape`, according to the `unknown_fields` option. Returns `Ok(())` if the
+/// property should be skipped.
+///
+/// This takes the options rather than the state, so it can be called while
+/// the field name borrows the string conversion buffer of the state.
+pub fn check_unknown_field<'shape>(
+    shape: &'shape Shape<'shape>,
+    field_name: &str,
+    options: &UnmarshalOptions,
+) -> Result<(), Error<'shape>> {
+    match options.unknown_fields {
+        UnknownFields::Ignore => Ok(()),
+        UnknownFields::Deny => Err(Error::UnknownField {
+            shape,
+            field: field_name.to_owned(),
+        }),
     }
+}
 
-    if partial.shape().has_default_attr() {
-        partial.fill_unset_fields_from_default()?;
+/// Read a property of `object` for unmarshalling.
+///
+/// With [`PropertyAccess::OwnDataOnly`], only own data properties are read
+/// through their property descriptor, so no JS code runs. Missing properties
+/// are `undefined`.
+pub fn get_property<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    key: v8::Local<'scope, v8::Value>,
+    shape: &'shape Shape<'shape>,
+    state: &UnmarshalState<'scope>,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
+    if state.options.property_access == PropertyAccess::Get {
+        return object.get(scope, key).ok_or_else(Error::exception);
     }
 
-    Ok(partial)
+    // Numeric keys are converted to strings, which cannot run JS code.
+    let name = match v8::Local::<v8::Name>::try_from(key) {
+        Ok(name) => name,
+        Err(_) => key.to_string(scope).ok_or_else(Error::exception)?.into(),
+    };
+    let descriptor = object
+        .get_own_property_descriptor(scope, name)
+        .ok_or_else(Error::exception)?;
+    let Ok(descriptor) = v8::Local::<v8::Object>::try_from(descriptor) else {
+        return Ok(v8::undefined(scope).into());
+    };
+    // The descriptor is a fresh ordinary object, but only its own properties
+    // can be trusted, because `Object.prototype` may have been tampered with.
+    for accessor in ["get", "set"] {
+        let accessor = v8::String::new(scope, accessor).unwrap();
+        if descriptor
+            .has_own_property(scope, accessor.into())
+            .ok_or_else(Error::exception)?
+        {
+            return Err(Error::AccessorProperty {
+                shape,
+                property: key.to_rust_string_lossy(scope),
+            });
+        }
+    }
+    let value = v8::String::new(scope, "value").unwrap();
+    descriptor
+        .get(scope, value.into())
+        .ok_or_else(Error::exception)
+}
+
+/// Read an element of the array-like `object` for unmarshalling. See
+/// [`get_property()`].
+pub fn get_index<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    index: u32,
+    shape: &'shape Shape<'shape>,
+    state: &UnmarshalState<'scope>,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
+    if state.options.property_access == PropertyAccess::Get {
+        return object.get_index(scope, index).ok_or_else(Error::exception);
+    }
+    let key = v8::Integer::new_from_unsigned(scope, index);
+    get_property(scope, object, key.into(), shape, state)
 }
@@ -0,0 +1,231 @@
+use facet_core::Field;
+
+use super::has_field_attribute;
+
+/// Options controlling how Rust values are converted to JS values.
+///
+/// Use with [`to_v8_with_options()`](crate::to_v8_with_options). The default
+/// options are the ones used by [`to_v8()`](crate::to_v8).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarshalOptions {
+    /// How to represent maps (`HashMap`, `BTreeMap`, etc.).
+    pub map_repr: MapRepr,
+    /// How to represent `u64`, `i64`, `usize`, and `isize`. Fields with the
+    /// `#[facet(js_number)]` attribute always use [`Int64Repr::Number`].
+    pub int64_repr: Int64Repr,
+    /// How to write the properties of objects and arrays.
+    pub property_write: PropertyWrite,
+}
+
+impl MarshalOptions {
+    /// The options in effect inside `field`.
+    pub(crate) fn for_field(mut self, field: &Field) -> Self {
+        if has_field_attribute(field, "js_number") {
+            self.int64_repr = Int64Repr::Number;
+        }
+        self
+    }
+}
+
+/// How properties are written to JS objects when marshalling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PropertyWrite {
+    /// Define data properties on objects created by `facet-v8`, and assign
+    /// properties of objects created by custom
+    /// [`Constructors`](crate::Constructors) or passed to
+    /// [`to_v8_into()`](crate::to_v8_into).
+    #[default]
+    Auto,
+    /// Always define own data properties, like `Object.defineProperty()`.
+    /// Setters and read-only properties on the prototype chain, including on
+    /// `Object.prototype`, cannot change what gets written.
+    Define,
+    /// Always assign properties, like `object[key] = value`, which invokes
+    /// setters.
+    Set,
+}
+
+/// Options controlling how JS values are converted to Rust values.
+///
+/// When unmarshalling values produced by untrusted JS code, set the `max_*`
+/// limits to bound the amount of memory and time spent.
+///
+/// Use with [`from_v8_with_options()`](crate::from_v8_with_options). The
+/// default options are the ones used by [`from_v8()`](crate::from_v8).
+#[derive(Debug, Clone, Copy)]
+pub struct UnmarshalOptions {
+    /// Which JS values are accepted as maps.
+    pub map_repr: MapRepr,
+    /// What to do with properties of JS objects that do not correspond to a
+    /// field of the struct.
+    pub unknown_fields: UnknownFields,
+    /// How to handle JS strings that are not valid UTF-16.
+    pub strings: StringConversion,
+    /// How typed arrays with a different element type are unmarshalled into
+    /// sequences of numbers.
+    pub typed_arrays: TypedArrayConversion,
+    /// Whether JS values of the wrong type are converted to scalars. Fields
+    /// with the `#[facet(js_lenient)]` or `#[facet(js_strict)]` attribute
+    /// override this.
+    pub coercion: Coercion,
+    /// How properties of JS objects are read. Use
+    /// [`PropertyAccess::OwnDataOnly`] to guarantee that unmarshalling does not
+    /// run any JS code.
+    pub property_access: PropertyAccess,
+    /// The maximum nesting depth of JS objects (including arrays), to avoid
+    /// overflowing the stack on deeply nested input. The default is 128.
+    pub max_depth: usize,
+    /// The maximum length of arrays and typed arrays. Unlimited by default.
+    pub max_array_len: Option<usize>,
+    /// The maximum length of strings in UTF-8 bytes. Unlimited by default.
+    pub max_string_len: Option<usize>,
+    /// The maximum number of entries in maps and sets. Unlimited by default.
+    pub max_entries: Option<usize>,
+    /// The maximum total number of JS values visited while unmarshalling,
+    /// including the root value. Unlimited by default.
+    pub max_nodes: Option<usize>,
+}
+
+impl Default for UnmarshalOptions {
+    fn default() -> Self {
+        UnmarshalOptions {
+            map_repr: MapRepr::default(),
+            unknown_fields: UnknownFields::default(),
+            strings: StringConversion::default(),
+            typed_arrays: TypedArrayConversion::default(),
+            coercion: Coercion::default(),
+            property_access: PropertyAccess::default(),
+            max_depth: 128,
+            max_array_len: None,
+            max_string_len: None,
+            max_entries: None,
+            max_nodes: None,
+        }
+    }
+}
+
+impl UnmarshalOptions {
+    /// The options in effect inside `field`.
+    pub(crate) fn for_field(mut self, field: &Field) -> Self {
+        if has_field_attribute(field, "js_lenient") {
+            self.coercion = Coercion::Lenient;
+        } else if has_field_attribute(field, "js_strict") {
+            self.coercion = Coercion::Strict;
+        }
+        self
+    }
+}
+
+/// The JS representation of Rust maps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapRepr {
+    /// Maps are JS `Map` objects, which support keys of any type.
+    #[default]
+    Map,
+    /// Maps are plain JS objects, so keys must be strings or numbers.
+    ///
+    /// When unmarshalling, `Map` objects are still accepted.
+    Object,
+}
+
+/// The JS representation of 64-bit integers.
+///
+/// JS numbers can only represent integers up to 2^53 - 1
+/// (`Number.MAX_SAFE_INTEGER`) exactly, so 64-bit integers do not fit in
+/// general.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Int64Repr {
+    /// Always marshal as `bigint`.
+    #[default]
+    BigInt,
+    /// Marshal as `number` when the value can be represented exactly, and as
+    /// `bigint` otherwise.
+    NumberIfSafe,
+    /// Always marshal as `number`, failing with
+    /// [`Error::IntOverflow`](crate::Error::IntOverflow) when the value cannot
+    /// be represented exactly.
+    Number,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Skip properties that do not correspond to a field. The JS side may add
+    /// any number of additional properties for all kinds of reasons, including
+    /// adding them in a custom constructor.
+    #[default]
+    Ignore,
+    /// Fail with [`Error::UnknownField`](crate::Error::UnknownField).
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringConversion {
+    /// Replace unpaired surrogates with U+FFFD REPLACEMENT CHARACTER.
+    #[default]
+    Lossy,
+    /// Fail if the string contains unpaired surrogates.
+    Strict,
+}
+
+/// Conversion of typed arrays and other buffers to sequences of numbers.
+///
+/// `ArrayBuffer`s, `SharedArrayBuffer`s and `DataView`s are always accepted
+/// for sequences of `u8` and `i8`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TypedArrayConversion {
+    /// Only accept typed arrays with the same element type, such as a
+    /// `Float32Array` for `Vec<f32>`.
+    #[default]
+    Exact,
+    /// Reinterpret the bytes of typed arrays with a different element type,
+    /// `ArrayBuffer`s, `SharedArrayBuffer`s and `DataView`s as the element
+    /// type, in native byte order. Like
+    /// `new Float32Array(buffer, byteOffset, length)`, the offset and length of
+    /// the memory in bytes must be multiples of the element size.
+    ///
+    /// The element type must be a primitive integer or floating point type
+    /// other than `u128` and `i128`. Other plain data types, such as
+    /// `#[repr(C)]` structs of numbers, are only reinterpreted in fields with
+    /// the `flat_array` attribute.
+    Reinterpret,
+}
+
+/// How properties are read from JS objects when unmarshalling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PropertyAccess {
+    /// Read properties like `object[key]`, which invokes getters and Proxy
+    /// traps.
+    #[default]
+    Get,
+    /// Read only own data properties through their property descriptors, so no
+    /// JS code runs during unmarshalling, even for hostile input:
+    ///
+    /// - Accessor properties (getters/setters) fail with
+    ///   [`Error::AccessorProperty`](crate::Error::AccessorProperty).
+    /// - Proxy objects fail with [`Error::Proxy`](crate::Error::Proxy).
+    /// - Inherited properties are ignored, and `__proto__` is an ordinary key.
+    OwnDataOnly,
+}
+
+/// Conversion of JS values to Rust scalars (numbers, booleans, strings, etc.).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Coercion {
+    /// Only accept values of the matching JS type: `number` or `bigint` for
+    /// numbers, `boolean` for `bool`, and `string` for strings.
+    ///
+    /// Values such as `"12"`, `true` and `null` are rejected for all number
+    /// types, including `f32` and `f64`.
+    #[default]
+    Strict,
+    /// Apply JS-style coercion:
+    ///
+    /// - Numbers from strings, booleans, `null` and `undefined`, like
+    ///   `Number(value)`. Integers must still be integral, so `"1.5"` and
+    ///   `undefined` (`NaN`) are rejected.
+    /// - Strings from numbers, bigints and booleans, like `String(value)`.
+    /// - Booleans from the truthiness of any value, like `Boolean(value)`.
+    ///
+    /// Objects are never coerced to numbers or strings, because that could run
+    /// arbitrary JS code (`valueOf()`, `toString()`).
+    Lenient,
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use facet_core::{Def, Field, PtrMut, Shape, StructKind, StructType, Type, UserType};
+use facet_reflect::{HasFields, Partial, Peek};
+
+use super::{Error, MarshalState, UnmarshalState};
+
+/// Update `object` in place to reflect `peek`, keeping its identity.
+///
+/// Structs update the properties of the object, recursing into nested objects
+/// that are compatible with the new value, and delete the properties of fields
+/// that are `None` or skipped. Arrays are updated element by element and
+/// truncated. Maps and sets are cleared and refilled.
+///
+/// Returns `false` without touching `object` if it cannot represent `peek`, in
+/// which case the caller should replace it with a newly marshalled value.
+pub fn marshal_into_existing<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
+    peek: Peek<'mem, 'facet, 'shape>,
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    state: &mut MarshalState<'mem, 'scope, '_, '_>,
+    field: Option<&Field>,
+) -> Result<bool, Error<'shape>> {
+    let peek = match peek.into_option() {
+        Ok(option) => match option.value() {
+            Some(peek) => peek,
+            None => return Ok(false),
+        },
+        Err(_) => peek,
+    };
+    let shape = peek.shape();
+
+    match shape.def {
+        Def::Map(_) => {
+            let Ok(map) = v8::Local::<v8::Map>::try_from(object) else {
+                return Ok(false);
+            };
+            map.clear();
+            super::map::marshal_map_into(peek, scope, object, state)?;
+            Ok(true)
+        }
+        Def::Set(_) => {
+            let Ok(set) = v8::Local::<v8::Set>::try_from(object) else {
+                return Ok(false);
+            };
+            set.clear();
+            super::set::marshal_set_into(peek, scope, object, state)?;
+            Ok(true)
+        }
+        Def::List(_) | Def::Array(_) | Def::Slice(_) => {
+            // Typed arrays cannot be resized, so they are always replaced.
+            let wants_typed_array = field.is_some_and(super::array::is_typed_array_field);
+            let Ok(array) = v8::Local::<v8::Array>::try_from(object) else {
+                return Ok(false);
+            };
+            if wants_typed_array {
+                return Ok(false);
+            }
+            patch_array(peek, scope, array, state)?;
+            Ok(true)
+        }
+        Def::Scalar(_) | Def::SmartPointer(_) | Def::Option(_) => Ok(false),
+        _ => match patchable_struct(shape) {
+            Some(struct_type) if !object.is_array() && !object.is_map() && !object.is_set() => {
+                patch_object(peek, scope, object, struct_type, state)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        },
+    }
+}
+
+fn patch_object<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
+    peek: Peek<'mem, 'facet, 'shape>,
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    struct_type: StructType<'shape>,
+    state: &mut MarshalState<'mem, 'scope, '_, '_>,
+) -> Result<(), Error<'shape>> {
+    let mut serialized = HashSet::new();
+    for (field, field_value) in peek.into_struct()?.fields_for_serialize() {
+        serialized.insert(field.name);
+        let key = property_key(scope, field.name)?.into();
+        let is_none = field_value
+            .into_option()
+            .is_ok_and(|option| option.value().is_none());
+        if is_none {
+            object.delete(scope, key).ok_or_else(Error::exception)?;
+            continue;
+        }
+
+        let existing = object.get(scope, key).ok_or_else(Error::exception)?;
+        if let Ok(existing) = v8::Local::<v8::Object>::try_from(existing) {
+            let options = state.options;
+            state.options = options.for_field(&field);
+            let updated =
+                marshal_into_existing(field_value, scope, existing, state, Some(&field));
+            state.options = options;
+            if updated.map_err(|err| err.at_field(field.name))? {
+                continue;
+            }
+        }
+        let value = super::marshal_field(field_value, scope, state, &field)
+            .map_err(|err| err.at_field(field.name))?;
+        super::object::set_property(scope, object, key, value, state, false)?;
+    }
+
+    // Delete the properties of skipped fields.
+    for field in struct_type.fields {
+        if !serialized.contains(field.name) {
+            let key = property_key(scope, field.name)?;
+            object.delete(scope, key.into()).ok_or_else(Error::exception)?;
+        }
+    }
+    Ok(())
+}
+
+fn patch_array<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
+    peek: Peek<'mem, 'facet, 'shape>,
+    scope: &mut v8::HandleScope<'scope>,
+    array: v8::Local<'scope, v8::Array>,
+    state: &mut MarshalState<'mem, 'scope, '_, '_>,
+) -> Result<(), Error<'shape>> {
+    let list = peek.into_list_like()?;
+    for (i, item) in list.iter().enumerate() {
+        let existing = array
+            .get_index(scope, i as u32)
+            .ok_or_else(Error::exception)?;
+        if let Ok(existing) = v8::Local::<v8::Object>::try_from(existing)
+            && marshal_into_existing(item, scope, existing, state, None)
+                .map_err(|err| err.at_index(i))?
+        {
+            continue;
+        }
+        let value =
+            super::marshal_value(item, scope, state, None).map_err(|err| err.at_index(i))?;
+        array
+            .set_index(scope, i as u32, value)
+            .ok_or_else(Error::exception)?;
+    }
+
+    // Truncate the array.
+    let length = property_key(scope, "length")?;
+    let len = v8::Integer::new_from_unsigned(scope, list.len() as u32);
+    array
+        .set(scope, length.into(), len.into())
+        .ok_or_else(Error::exception)?;
+    Ok(())
+}
+
+fn property_key<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    name: &str,
+) -> Result<v8::Local<'scope, v8::String>, Error<'shape>> {
+    v8::String::new_from_utf8(scope, name.as_bytes(), v8::NewStringType::Internalized)
+        .ok_or_else(Error::exception)
+}
+
+/// Update the value at `target` with the contents of `value`.
+///
+/// Structs are updated field by field from the own properties of the JS object,
+/// recursing into nested structs. Fields that are missing from the JS object
+/// are left untouched. Everything else is replaced by a freshly unmarshalled
+/// value.
+///
+/// # Safety
+///
+/// `target` must point to a valid, exclusively borrowed value of type `shape`.
+pub unsafe fn patch_value<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    target: PtrMut<'_>,
+    shape: &'shape Shape<'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<(), Error<'shape>> {
+    if let Some(struct_type) = patchable_struct(shape)
+        && let Ok(object) = v8::Local::<v8::Object>::try_from(value)
+        && !object.is_array()
+    {
+        // The same limits apply as when unmarshalling a new value.
+        state.count_node(shape)?;
+        state.enter_object(object, shape)?;
+        let result = unsafe { patch_struct(scope, object, target, shape, struct_type, state) };
+        state.leave_object(object);
+        return result;
+    }
+    unsafe { replace_value(scope, value, target, shape, state) }
+}
+
+/// Structs with named fields are patched field by field. Tuple structs are
+/// arrays on the JS side, so they are replaced as a whole.
+fn patchable_struct<'shape>(shape: &'shape Shape<'shape>) -> Option<StructType<'shape>> {
+    match shape.ty {
+        Type::User(UserType::Struct(struct_type)) if struct_type.kind == StructKind::Struct => {
+            Some(struct_type)
+        }
+        _ => None,
+    }
+}
+
+/// Patch the fields of a struct for which `object` has own properties. Other
+/// properties are handled like in `unmarshal_struct()`.
+unsafe fn patch_struct<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    target: PtrMut<'_>,
+    shape: &'shape Shape<'shape>,
+    struct_type: StructType<'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<(), Error<'shape>> {
+    let property_names = super::object::own_property_names(scope, object)?;
+    for i in 0..property_names.length() {
+        let key = property_names.get_index(scope, i).ok_or_else(Error::exception)?;
+        let key = v8::Local::<v8::String>::try_from(key)
+            .expect("v8::GetPropertyNames() returned a non-string key");
+        let field_name = key.to_rust_string_lossy(scope);
+        // Unknown properties are skipped before reading them.
+        let Some(field) = struct_type.fields.iter().find(|field| field.name == field_name) else {
+            super::object::check_unknown_field(shape, &field_name, &state.options)?;
+            continue;
+        };
+        let value = super::object::get_property(scope, object, key.into(), field.shape(), state)?;
+        // SAFETY: The field is part of the struct at `target`.
+        let field_target = unsafe { PtrMut::new(target.as_mut_byte_ptr().add(field.offset)) };
+        let (options, flat_array) = (state.options, state.flat_array);
+        state.options = options.for_field(field);
+        state.flat_array = super::has_field_attribute(field, "flat_array");
+        let result = unsafe { patch_value(scope, value, field_target, field.shape(), state) };
+        (state.options, state.flat_array) = (options, flat_array);
+        result.map_err(|err| err.at_field(field.name))?;
+    }
+    Ok(())
+}
+
+/// Unmarshal `value` into a new value, and swap it into `target`. The old value
+/// is dropped.
+unsafe fn replace_value<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    target: PtrMut<'_>,
+    shape: &'shape Shape<'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<(), Error<'shape>> {
+    let size = shape
+        .layout
+        .sized_layout()
+        .expect("cannot unmarshal into unsized values")
+        .size();
+
+    let mut partial = Partial::alloc_shape(shape)?;
+    super::unmarshal_value(scope, value, &mut partial, state)?;
+    let new = partial.build()?;
+
+    // SAFETY: Both are valid values of the same type. After the swap, the old
+    // value is dropped along with `new`.
+    unsafe {
+        std::ptr::swap_nonoverlapping(
+            target.as_mut_byte_ptr(),
+            new.peek().data().thin().unwrap().as_byte_ptr() as *mut u8,
+            size,
+        );
+    }
+    Ok(())
+}
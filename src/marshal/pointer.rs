@@ -1,63 +1,317 @@
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::mem::MaybeUninit;
 
-use facet_core::{Def, Field, KnownSmartPointer, PointerType, PtrConst, SmartPointerDef};
+use facet_core::{
+    BorrowFn, CloneIntoFn, ConstTypeId, Def, DowngradeIntoFn, DropInPlaceFn, Field,
+    KnownSmartPointer, PointerType, PtrConst, PtrConstWide, PtrMut, PtrUninit, Shape,
+    SmartPointerDef, Type, ValuePointerType,
+};
 use facet_reflect::{Partial, Peek, PeekSmartPointer, ReflectError};
 
-use super::{Error, MarshalState, UnmarshalState, will_marshal_as_object};
+use super::arena::RawSlice;
+use super::flat::FlatElement;
+use super::{Arena, Error, MarshalState, UnmarshalState, will_marshal_as_object};
 
 #[derive(Default)]
 pub struct MarshalPointers<'mem, 'scope> {
-    shared_pointers: HashMap<PtrConst<'mem>, v8::Local<'scope, v8::Object>>,
+    shared_pointers: HashMap<SharedKey<'mem>, v8::Local<'scope, v8::Object>>,
+    /// Strong references obtained by upgrading weak pointers. These are kept
+    /// alive until marshalling is done, so the addresses of their pointees
+    /// cannot be reused by other values in the meantime.
+    upgraded: Vec<SharedPointer>,
+}
+
+/// Identifies a value that may be referenced from multiple places.
+///
+/// The type is part of the key, because a struct and its first field have the
+/// same address, and the length is part of the key, because slices with
+/// different lengths may start at the same address.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct SharedKey<'mem> {
+    ptr: PtrConst<'mem>,
+    len: usize,
+    type_id: ConstTypeId,
 }
 
 #[derive(Default)]
-pub struct UnmarshalPointers<'mem, 'scope> {
-    objects: HashMap<v8::Local<'scope, v8::Object>, PtrConst<'mem>>,
+pub struct UnmarshalPointers<'scope> {
+    /// Shared pointers that were created for JS objects, by object and pointer
+    /// type. When the same object is encountered again as the same type, the
+    /// pointer is cloned instead of unmarshalling the object again, so object
+    /// identities are preserved.
+    objects: HashMap<(v8::Local<'scope, v8::Object>, ConstTypeId), SharedPointer>,
+    /// Objects and pointer types for which a shared pointer is currently being
+    /// constructed. A strong reference back to them is a cycle.
+    in_progress: HashSet<(v8::Local<'scope, v8::Object>, ConstTypeId)>,
+    /// Shared pointers reserved for objects in `in_progress`, because a weak
+    /// pointer refers back to them. They are filled in once the object is
+    /// done, like with `Rc::new_cyclic()`.
+    reserved: HashMap<(v8::Local<'scope, v8::Object>, ConstTypeId), SharedPointer>,
+    /// Arena allocations created for references to JS objects, by object and
+    /// target type. The value is the address and length (for slices).
+    references: HashMap<(v8::Local<'scope, v8::Object>, ConstTypeId), (*const u8, usize)>,
+}
+
+/// A type-erased strong reference (`Rc<T>` or `Arc<T>`).
+struct SharedPointer {
+    // Smart pointers are at most two words (fat pointers to slices or `str`).
+    storage: MaybeUninit<[usize; 2]>,
+    /// The type of the strong pointer, which is checked before the pointer is
+    /// moved into a partial.
+    type_id: ConstTypeId,
+    clone_into: Option<CloneIntoFn>,
+    drop_in_place: DropInPlaceFn,
+    borrow: BorrowFn,
+    downgrade_into: Option<DowngradeIntoFn>,
+}
+
+impl SharedPointer {
+    /// Take ownership of the strong pointer in `storage`.
+    ///
+    /// # Safety
+    ///
+    /// `storage` must contain an initialized value of `shape`.
+    unsafe fn from_storage(storage: MaybeUninit<[usize; 2]>, shape: &Shape) -> Self {
+        let Def::SmartPointer(def) = shape.def else {
+            panic!("expected a smart pointer shape");
+        };
+        let vtable = shape.vtable.sized().expect("smart pointers must be sized");
+        SharedPointer {
+            storage,
+            type_id: shape.id,
+            clone_into: (vtable.clone_into)(),
+            drop_in_place: (vtable.drop_in_place)().expect("smart pointers must implement Drop"),
+            borrow: def
+                .vtable
+                .borrow_fn
+                .expect("shared pointers must support borrowing"),
+            downgrade_into: def.vtable.downgrade_into_fn,
+        }
+    }
+
+    /// Create a strong pointer of `shape` (an `Rc<T>` or `Arc<T>`) that owns
+    /// the value in `pointee`, which is moved out.
+    fn new<'shape>(
+        shape: &'shape Shape<'shape>,
+        pointee: &mut RawSlice,
+    ) -> Result<Self, Error<'shape>> {
+        let Def::SmartPointer(def) = shape.def else {
+            panic!("expected a smart pointer shape");
+        };
+        let new_into = def.vtable.new_into_fn.ok_or(ReflectError::OperationFailed {
+            shape,
+            operation: "shared pointer type does not support creating pointers",
+        })?;
+        assert_eq!(pointee.len(), 1, "expected exactly one pointee");
+        let mut storage = Self::storage_for(shape);
+        unsafe {
+            new_into(PtrUninit::new(storage.as_mut_ptr()), pointee.as_mut_ptr());
+            pointee.forget_values();
+            Ok(Self::from_storage(storage, shape))
+        }
+    }
+
+    /// Create a strong pointer of `shape` to the default value of the pointee
+    /// type, which is replaced with [`fill()`](Self::fill) once the actual
+    /// pointee is built. Returns `None` if the pointee type does not implement
+    /// `Default`.
+    fn reserve<'shape>(shape: &'shape Shape<'shape>) -> Option<Self> {
+        let pointee_shape = pointee_shape(shape).ok()?;
+        pointee_shape
+            .vtable
+            .sized()
+            .and_then(|vtable| (vtable.default_in_place)())?;
+        let mut pointee = RawSlice::with_capacity(pointee_shape, 1);
+        pointee
+            .push_with(pointee_shape, |partial| {
+                partial.set_default()?;
+                Ok(())
+            })
+            .ok()?;
+        Self::new(shape, &mut pointee).ok()
+    }
+
+    /// Replace the pointee of a pointer created with [`reserve()`](Self::reserve)
+    /// with the value in `pointee`, which is moved out.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must not have been cloned, and its pointee must not be
+    /// borrowed. Weak pointers to it may exist, as long as none of them was
+    /// upgraded. `pointee` must hold one value of the pointee type.
+    unsafe fn fill(&self, pointee_shape: &Shape, pointee: &mut RawSlice) {
+        let size = pointee_shape
+            .layout
+            .sized_layout()
+            .expect("shared pointers must have a sized pointee")
+            .size();
+        // The borrow function of `Rc` and `Arc` is `as_ptr()`, which does not
+        // go through a reference, so the pointer may be written to while
+        // nothing else can observe the pointee.
+        let target = self.pointee().as_byte_ptr() as *mut u8;
+        unsafe {
+            if let Some(drop_in_place) = pointee_shape
+                .vtable
+                .sized()
+                .and_then(|vtable| (vtable.drop_in_place)())
+            {
+                drop_in_place(PtrMut::new(target));
+            }
+            std::ptr::copy_nonoverlapping(pointee.as_ptr().as_byte_ptr(), target, size);
+            pointee.forget_values();
+        }
+    }
+
+    /// Upgrade the weak pointer in `weak`, returning `None` if the pointee was
+    /// already dropped.
+    fn upgrade(weak: PtrConst, def: &SmartPointerDef) -> Option<Self> {
+        let strong_shape = def.strong.expect("weak pointers must have a strong type")();
+        let upgrade_into = def
+            .vtable
+            .upgrade_into_fn
+            .expect("weak pointers must support upgrading");
+        let mut storage = Self::storage_for(strong_shape);
+        unsafe {
+            upgrade_into(
+                PtrMut::new(weak.as_byte_ptr() as *mut u8),
+                PtrUninit::new(storage.as_mut_ptr()),
+            )?;
+            Some(Self::from_storage(storage, strong_shape))
+        }
+    }
+
+    fn storage_for(shape: &Shape) -> MaybeUninit<[usize; 2]> {
+        let layout = shape
+            .layout
+            .sized_layout()
+            .expect("smart pointers must be sized");
+        assert!(
+            layout.size() <= size_of::<[usize; 2]>() && layout.align() <= align_of::<usize>(),
+            "unexpected smart pointer layout: {shape}"
+        );
+        MaybeUninit::uninit()
+    }
+
+    /// Pointer to the value behind the smart pointer.
+    fn pointee(&self) -> PtrConst<'_> {
+        unsafe { (self.borrow)(PtrConst::new(self.storage.as_ptr())) }
+    }
+
+    /// Populate `partial` with a clone of this pointer.
+    fn clone_into<'partial, 'facet, 'shape>(
+        &self,
+        partial: &'partial mut Partial<'facet, 'shape>,
+    ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+        let shape = partial.shape();
+        if shape.id != self.type_id {
+            return Err(ReflectError::OperationFailed {
+                shape,
+                operation: "shared pointer was created for a different type",
+            }
+            .into());
+        }
+        let clone_into = self.clone_into.ok_or(ReflectError::OperationFailed {
+            shape,
+            operation: "shared pointer type does not implement Clone",
+        })?;
+        let mut storage = MaybeUninit::<[usize; 2]>::uninit();
+        unsafe {
+            let cloned = clone_into(
+                PtrConst::new(self.storage.as_ptr()),
+                PtrUninit::new(storage.as_mut_ptr()),
+            );
+            // `set_shape()` moves the clone into the partial.
+            if let Err(err) = partial.set_shape(cloned.as_const(), shape) {
+                (self.drop_in_place)(cloned);
+                return Err(err.into());
+            }
+        }
+        Ok(partial)
+    }
+
+    /// Populate `partial` (a weak pointer) with a downgraded clone of this
+    /// pointer.
+    fn downgrade_into<'partial, 'facet, 'shape>(
+        &self,
+        partial: &'partial mut Partial<'facet, 'shape>,
+    ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+        let shape = partial.shape();
+        let Def::SmartPointer(def) = shape.def else {
+            panic!("expected a smart pointer shape");
+        };
+        if def.strong.is_none_or(|strong| strong().id != self.type_id) {
+            return Err(ReflectError::OperationFailed {
+                shape,
+                operation: "shared pointer was created for a different type",
+            }
+            .into());
+        }
+        let downgrade_into = self.downgrade_into.ok_or(ReflectError::OperationFailed {
+            shape,
+            operation: "shared pointer type does not support weak pointers",
+        })?;
+        let drop_weak = shape
+            .vtable
+            .sized()
+            .and_then(|vtable| (vtable.drop_in_place)())
+            .expect("weak pointers must implement Drop");
+        let mut storage = SharedPointer::storage_for(shape);
+        // Downgrading takes a mutable pointer, so pass a bitwise copy of the
+        // strong pointer. The copy is never dropped, so it does not affect the
+        // reference counts.
+        let mut strong = self.storage;
+        unsafe {
+            let weak = downgrade_into(
+                PtrMut::new(strong.as_mut_ptr()),
+                PtrUninit::new(storage.as_mut_ptr()),
+            );
+            // `set_shape()` moves the weak pointer into the partial.
+            if let Err(err) = partial.set_shape(weak.as_const(), shape) {
+                drop_weak(weak);
+                return Err(err.into());
+            }
+        }
+        Ok(partial)
+    }
+}
+
+impl Drop for SharedPointer {
+    fn drop(&mut self) {
+        unsafe {
+            (self.drop_in_place)(PtrMut::new(self.storage.as_mut_ptr()));
+        }
+    }
 }
 
 pub fn marshal_smart_pointer<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
-    peek: PeekSmartPointer<'mem, 'facet, 'shape>,
+    peek: Peek<'mem, 'facet, 'shape>,
     scope: &mut v8::HandleScope<'scope>,
     state: &mut MarshalState<'mem, 'scope, '_, '_>,
     field: Option<&Field>,
 ) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
-    let (is_shared, is_weak) = match peek.def().known {
+    let smart_pointer = peek.into_smart_pointer()?;
+    let (is_shared, is_weak) = match smart_pointer.def().known {
         Some(KnownSmartPointer::Arc | KnownSmartPointer::Rc) => (true, false),
         Some(KnownSmartPointer::ArcWeak | KnownSmartPointer::RcWeak) => (true, true),
         _ => (false, false),
     };
 
     if is_weak {
-        unimplemented!("weak smart pointers are not supported (yet)");
+        return marshal_weak_pointer(peek, smart_pointer, scope, state, field);
     }
 
-    let Some(pointee) = peek.borrow_inner() else {
+    let Some(pointee) = smart_pointer.borrow_inner() else {
         return Ok(state.null.into());
     };
 
-    // TODO: Once we gain general support for references (immutable borrows),
-    // all pointers are essentially shared pointers.
-
+    // `Rc`/`Arc` pointees are keyed by address like the targets of references
+    // (see `marshal_pointer()`), so all pointers and references to the same
+    // value share one JS object. A `Box` owns its pointee, so nothing else can
+    // point to it.
     if is_shared && will_marshal_as_object(pointee.shape()) {
-        let ptr = pointee
-            .data()
-            .thin()
-            .expect("DST shared pointers are not supported (yet)");
-
-        if let Some(shared) = state.pointers.shared_pointers.get(&ptr) {
-            // We already serialized this pointer, so just return the existing
-            // object.
-            return Ok((*shared).into());
-        }
-
-        // We didn't, let's create the object.
-        let obj = super::object::create_object_for_shape(pointee, scope, state, field)?;
-        // Insert the object into the shared pointers map before populating it,
-        // in case there are circular references.
-        state.pointers.shared_pointers.insert(ptr, obj);
-        // Finally populate the object with the pointee's fields.
-        super::marshal_into_object(pointee, scope, obj, state)?;
-        Ok(obj.into())
+        let key = SharedKey::thin(pointee);
+        marshal_shared_object(pointee, key, scope, state, field)
     } else {
         // Not a shared pointer, or the pointee is not an object, so just
         // serialize it as a direct value.
@@ -65,11 +319,94 @@ pub fn marshal_smart_pointer<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     }
 }
 
+/// Marshal the pointee of a weak pointer as a shared object, or `null` if the
+/// pointee was already dropped.
+fn marshal_weak_pointer<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
+    peek: Peek<'mem, 'facet, 'shape>,
+    smart_pointer: PeekSmartPointer<'mem, 'facet, 'shape>,
+    scope: &mut v8::HandleScope<'scope>,
+    state: &mut MarshalState<'mem, 'scope, '_, '_>,
+    field: Option<&Field>,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
+    let def = smart_pointer.def();
+    let weak = peek.data().thin().expect("smart pointers must be sized");
+    let Some(strong) = SharedPointer::upgrade(weak, def) else {
+        return Ok(state.null.into());
+    };
+    let pointee_shape = def.pointee().expect("weak pointers must have a pointee");
+
+    // SAFETY: The strong reference is kept alive in `state` for the rest of
+    // the marshalling, and the pointee lives in the shared allocation, so it
+    // does not move when `strong` does.
+    let pointee = unsafe {
+        Peek::unchecked_new(
+            PtrConst::new(strong.pointee().as_byte_ptr()),
+            pointee_shape,
+        )
+    };
+    state.pointers.upgraded.push(strong);
+
+    if will_marshal_as_object(pointee_shape) {
+        let key = SharedKey::thin(pointee);
+        marshal_shared_object(pointee, key, scope, state, field)
+    } else {
+        super::marshal_value(pointee, scope, state, field)
+    }
+}
+
+impl<'mem> SharedKey<'mem> {
+    fn thin(pointee: Peek<'mem, '_, '_>) -> Self {
+        SharedKey {
+            ptr: pointee
+                .data()
+                .thin()
+                .expect("DST shared pointers are not supported (yet)"),
+            len: 0,
+            type_id: pointee.shape().id,
+        }
+    }
+}
+
+/// Marshal the pointee of a shared pointer or reference, reusing the JS object
+/// if the same pointee was already marshalled.
+fn marshal_shared_object<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
+    pointee: Peek<'mem, 'facet, 'shape>,
+    key: SharedKey<'mem>,
+    scope: &mut v8::HandleScope<'scope>,
+    state: &mut MarshalState<'mem, 'scope, '_, '_>,
+    field: Option<&Field>,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
+    if let Some(shared) = state.pointers.shared_pointers.get(&key) {
+        // We already serialized this pointer, so just return the existing
+        // object.
+        return Ok((*shared).into());
+    }
+
+    // Slices in `flat_array` fields, such as `&[[f32; 3]]`, are one flat
+    // buffer, which is shared like any other object.
+    if let Some(result) = super::flat::marshal_flat_array(pointee, scope, field) {
+        let value = result?;
+        if let Ok(obj) = v8::Local::<v8::Object>::try_from(value) {
+            state.pointers.shared_pointers.insert(key, obj);
+        }
+        return Ok(value);
+    }
+
+    // We didn't, let's create the object.
+    let obj = super::object::create_object_for_shape(pointee, scope, state, field)?;
+    // Insert the object into the shared pointers map before populating it,
+    // in case there are circular references.
+    state.pointers.shared_pointers.insert(key, obj);
+    // Finally populate the object with the pointee's fields.
+    super::marshal_into_object(pointee, scope, obj, state)?;
+    Ok(obj.into())
+}
+
 pub fn unmarshal_smart_pointer<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut facet_reflect::Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
     let Def::SmartPointer(SmartPointerDef { known, .. }) = shape.def else {
@@ -82,14 +419,17 @@ pub fn unmarshal_smart_pointer<'scope, 'partial, 'facet, 'shape: 'facet>(
     };
 
     if is_weak {
-        unimplemented!("weak smart pointers are not supported (yet)");
+        return unmarshal_weak_pointer(scope, value, partial, state);
     }
 
-    // TODO: Once we gain general support for references (immutable borrows),
-    // all pointers are essentially shared pointers.
-
+    // Each JS object becomes one `Rc`/`Arc`, which is cloned for every further
+    // occurrence of the object. References get the same treatment through the
+    // arena (see `unmarshal_pointer()`), and a `Box` always gets its own copy.
     if is_shared {
-        unimplemented!("shared smart pointers are not supported (yet)");
+        // Primitives have no identity in JS, so only objects can be shared.
+        if let Ok(object) = v8::Local::<v8::Object>::try_from(value) {
+            return unmarshal_shared_object(scope, object, partial, state);
+        }
     }
 
     super::unmarshal_value(scope, value, partial.begin_smart_ptr()?, state)?
@@ -97,25 +437,136 @@ pub fn unmarshal_smart_pointer<'scope, 'partial, 'facet, 'shape: 'facet>(
         .map_err(Into::into)
 }
 
-pub fn marshal_pointer<'mem, 'facet, 'shape, 'scope>(
+/// Unmarshal a JS object into a weak pointer to the `Rc<T>` or `Arc<T>`
+/// created for the same object.
+///
+/// If the object has not been unmarshalled yet, a strong pointer is created for
+/// it, which stays alive until unmarshalling is done. If nothing else holds a
+/// strong reference by then, the weak pointer will be dead.
+///
+/// A weak pointer to an object that is still being unmarshalled, such as the
+/// parent of a node in a tree, points to an allocation reserved for the strong
+/// pointer, which holds the default value of `T` until the object is done. If
+/// `T` does not implement `Default`, this fails with [`Error::Cycle`].
+fn unmarshal_weak_pointer<'scope, 'partial, 'facet, 'shape: 'facet>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let shape = partial.shape();
+    if value.is_null_or_undefined() {
+        // Dead weak pointer.
+        return partial.set_default().map_err(Into::into);
+    }
+    let Ok(object) = v8::Local::<v8::Object>::try_from(value) else {
+        return Err(Error::unexpected(shape, value.type_repr()));
+    };
+
+    let Def::SmartPointer(def) = shape.def else {
+        panic!("expected a smart pointer shape");
+    };
+    let strong_shape = def.strong.expect("weak pointers must have a strong type")();
+    let key = (object, strong_shape.id);
+    if state.pointers.in_progress.contains(&key) {
+        let reserved = match state.pointers.reserved.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(SharedPointer::reserve(strong_shape).ok_or(Error::Cycle(shape))?)
+            }
+        };
+        return reserved.downgrade_into(partial);
+    }
+
+    if !state.pointers.objects.contains_key(&key) {
+        let mut strong = Partial::alloc_shape(strong_shape)?;
+        unmarshal_shared_object(scope, object, &mut strong, state)?;
+        drop(strong.build()?);
+    }
+
+    state.pointers.objects[&key].downgrade_into(partial)
+}
+
+/// Unmarshal a JS object into an `Rc<T>` or `Arc<T>`, reusing the pointer if
+/// the same object was already unmarshalled as the same type.
+fn unmarshal_shared_object<'scope, 'partial, 'facet, 'shape: 'facet>(
+    scope: &mut v8::HandleScope<'scope>,
+    object: v8::Local<'scope, v8::Object>,
+    partial: &'partial mut Partial<'facet, 'shape>,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
+    let shape = partial.shape();
+    let key = (object, shape.id);
+    if let Some(shared) = state.pointers.objects.get(&key) {
+        // We already unmarshalled this object, so just clone the pointer.
+        return shared.clone_into(partial);
+    }
+
+    if !state.pointers.in_progress.insert(key) {
+        return Err(Error::Cycle(shape));
+    }
+
+    // Build the pointee separately, so we can hold on to a strong reference
+    // for any later occurrences of the same object.
+    let pointee_shape = pointee_shape(shape)?;
+    let mut pointee = RawSlice::with_capacity(pointee_shape, 1);
+    pointee.push_with(pointee_shape, |pointee| {
+        super::unmarshal_value(scope, object.into(), pointee, state)?;
+        Ok(())
+    })?;
+    state.pointers.in_progress.remove(&key);
+
+    let shared = match state.pointers.reserved.remove(&key) {
+        Some(reserved) => {
+            // SAFETY: Reserved pointers are only downgraded while the object
+            // is in progress, and weak pointers are not upgraded while
+            // unmarshalling.
+            unsafe { reserved.fill(pointee_shape, &mut pointee) };
+            reserved
+        }
+        None => SharedPointer::new(shape, &mut pointee)?,
+    };
+    state.pointers.objects.insert(key, shared);
+    state.pointers.objects[&key].clone_into(partial)
+}
+
+/// The pointee type of a shared pointer type.
+fn pointee_shape<'shape>(
+    shape: &'shape Shape<'shape>,
+) -> Result<&'shape Shape<'shape>, Error<'shape>> {
+    let Def::SmartPointer(def) = shape.def else {
+        panic!("expected a smart pointer shape");
+    };
+    def.pointee().ok_or_else(|| {
+        ReflectError::OperationFailed {
+            shape,
+            operation: "shared pointer type does not have a pointee type",
+        }
+        .into()
+    })
+}
+
+pub fn marshal_pointer<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
     peek: Peek<'mem, 'facet, 'shape>,
     pointer_type: PointerType<'shape>,
     scope: &mut v8::HandleScope<'scope>,
     state: &mut MarshalState<'mem, 'scope, '_, '_>,
+    field: Option<&Field>,
 ) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
     match pointer_type {
-        PointerType::Reference(_) => {
+        PointerType::Reference(vpt) => {
             if let Ok(s) = peek.get::<&str>() {
                 let s = v8::String::new(scope, s).expect("string too large");
-                Ok(s.into())
+                return Ok(s.into());
+            }
+
+            let (pointee, key) = deref_reference(peek, vpt);
+            if will_marshal_as_object(pointee.shape()) {
+                // References are shared pointers without ownership, so the
+                // same rules apply.
+                marshal_shared_object(pointee, key, scope, state, field)
             } else {
-                // TODO: Need access to the pointee through facet. When that
-                // lands, all pointers essentially become shared pointers.
-                _ = state;
-                Err(Error::Reflect(ReflectError::OperationFailed {
-                    shape: peek.shape(),
-                    operation: "cannot serialize reference to non-string type (yet)",
-                }))
+                super::marshal_value(pointee, scope, state, field)
             }
         }
         PointerType::Raw(_) => Err(Error::Reflect(ReflectError::OperationFailed {
@@ -129,15 +580,157 @@ pub fn marshal_pointer<'mem, 'facet, 'shape, 'scope>(
     }
 }
 
+/// Get the target of a reference (`&T`, `&[T]`, etc.), and the key identifying
+/// it in [`MarshalPointers`].
+fn deref_reference<'mem, 'facet: 'mem, 'shape: 'facet>(
+    peek: Peek<'mem, 'facet, 'shape>,
+    vpt: ValuePointerType<'shape>,
+) -> (Peek<'mem, 'facet, 'shape>, SharedKey<'mem>) {
+    let target = (vpt.target)();
+    let ptr = peek
+        .data()
+        .thin()
+        .expect("references are always sized")
+        .as_byte_ptr();
+
+    // SAFETY: The shape says that `peek` is a reference to `target`, and the
+    // referenced value outlives `'facet`.
+    unsafe {
+        if vpt.wide {
+            // Wide references to slices and `str` all have the same layout;
+            // only the meaning of the length differs.
+            let wide = *(ptr as *const *const [u8]);
+            let pointee = Peek::unchecked_new(PtrConstWide::new(wide), target);
+            let key = SharedKey {
+                ptr: PtrConst::new(wide as *const u8),
+                len: wide.len(),
+                type_id: target.id,
+            };
+            (pointee, key)
+        } else {
+            let thin = *(ptr as *const *const u8);
+            let pointee = Peek::unchecked_new(PtrConst::new(thin), target);
+            (pointee, SharedKey::thin(pointee))
+        }
+    }
+}
+
 pub fn unmarshal_pointer<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut facet_reflect::Partial<'facet, 'shape>, Error<'shape>> {
-    _ = (scope, value, state);
-    unimplemented!(
-        "unmarshaling pointers/references is not supported yet: {}",
-        partial.shape()
-    );
+    let shape = partial.shape();
+    let Type::Pointer(PointerType::Reference(vpt)) = shape.ty else {
+        return Err(ReflectError::OperationFailed {
+            shape,
+            operation: "cannot deserialize raw pointers or function pointers",
+        }
+        .into());
+    };
+    if vpt.mutable {
+        return Err(ReflectError::OperationFailed {
+            shape,
+            operation: "cannot deserialize mutable references",
+        }
+        .into());
+    }
+    let Some(arena) = state.arena() else {
+        return Err(ReflectError::OperationFailed {
+            shape,
+            operation: "deserializing references requires an arena; use `from_v8_in()`",
+        }
+        .into());
+    };
+
+    let target = (vpt.target)();
+    let object = v8::Local::<v8::Object>::try_from(value).ok();
+    let key = object.map(|object| (object, target.id));
+    let existing = key.and_then(|key| state.pointers.references.get(&key).copied());
+
+    let (ptr, len) = match existing {
+        Some(existing) => existing,
+        None if vpt.wide => unmarshal_slice_in(scope, value, target, arena, state)?,
+        None => {
+            let mut pointee = Partial::alloc_shape(target)?;
+            super::unmarshal_value(scope, value, &mut pointee, state)?;
+            let pointee = pointee.build()?;
+            // SAFETY: The pointee was unmarshalled with the same arena, so the
+            // only things it can borrow are earlier allocations in it.
+            let ptr = unsafe { arena.alloc_value(pointee) };
+            (ptr.as_byte_ptr(), 0)
+        }
+    };
+    if let Some(key) = key {
+        state.pointers.references.insert(key, (ptr, len));
+    }
+
+    // SAFETY: The arena outlives the value being unmarshalled, and `set_shape`
+    // copies the reference itself into the partial.
+    unsafe {
+        if vpt.wide {
+            let wide: *const [u8] = std::ptr::slice_from_raw_parts(ptr, len);
+            partial.set_shape(PtrConst::new(&raw const wide), shape)?;
+        } else {
+            partial.set_shape(PtrConst::new(&raw const ptr), shape)?;
+        }
+    }
+    Ok(partial)
+}
+
+/// Unmarshal an array-like JS object into a slice allocated in the arena.
+fn unmarshal_slice_in<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    target: &'shape Shape<'shape>,
+    arena: &Arena,
+    state: &mut UnmarshalState<'scope>,
+) -> Result<(*const u8, usize), Error<'shape>> {
+    let Def::Slice(slice_def) = target.def else {
+        return Err(ReflectError::OperationFailed {
+            shape: target,
+            operation: "cannot deserialize references to unsized types other than slices",
+        }
+        .into());
+    };
+    let item_shape = slice_def.t();
+    let flat_array = std::mem::take(&mut state.flat_array);
+    if let (Ok(object), Some(element)) = (
+        v8::Local::<v8::Object>::try_from(value),
+        FlatElement::of(item_shape).filter(|_| flat_array && !value.is_array()),
+    ) {
+        // A slice in a `flat_array` field, read from a flat buffer.
+        let memory = super::flat::flat_array_memory(scope, object, target, element, state)?;
+        let mut items = Vec::with_capacity(memory.byte_length / element.size());
+        for chunk in memory.bytes().chunks_exact(element.size()) {
+            let mut partial = Partial::alloc_shape(item_shape)?;
+            // SAFETY: See `unmarshal_flat_array()`.
+            unsafe { partial.set_shape(PtrConst::new(chunk.as_ptr()), item_shape)? };
+            items.push(partial.build()?);
+        }
+        let ptr = arena.alloc_slice_cloned(item_shape, &items)?;
+        return Ok((ptr.as_byte_ptr(), items.len()));
+    }
+
+    let len = if let Ok(array) = v8::Local::<v8::Array>::try_from(value) {
+        array.length()
+    } else if let Ok(typed_array) = v8::Local::<v8::TypedArray>::try_from(value) {
+        typed_array.length() as u32
+    } else {
+        return Err(Error::unexpected(target, value.type_repr()));
+    };
+    state.check_array_len(target, len as usize)?;
+    let object = v8::Local::<v8::Object>::try_from(value).unwrap();
+
+    let mut items = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let item = super::object::get_index(scope, object, i, target, state)?;
+        let mut partial = Partial::alloc_shape(item_shape)?;
+        super::unmarshal_value(scope, item, &mut partial, state)
+            .map_err(|err| err.at_index(i as usize))?;
+        items.push(partial.build()?);
+    }
+    let ptr = arena.alloc_slice_cloned(item_shape, &items)?;
+    Ok((ptr.as_byte_ptr(), items.len()))
 }
@@ -2,8 +2,8 @@ use std::borrow::Cow;
 
 use crate::marshal::UnmarshalState;
 
-use super::{Error, MarshalState};
-use facet_core::Shape;
+use super::{Coercion, Error, Int64Repr, MarshalState, StringConversion};
+use facet_core::{PtrConst, Shape};
 use facet_reflect::{Partial, Peek, ReflectError, ScalarType};
 
 pub fn scalar_to_v8<'mem, 'facet, 'shape, 'scope>(
@@ -58,7 +58,7 @@ pub fn scalar_to_v8<'mem, 'facet, 'shape, 'scope>(
         }
         ScalarType::U64 => {
             let u = *peek.get::<u64>().unwrap();
-            Ok(v8::BigInt::new_from_u64(scope, u).into())
+            int64_to_v8(scope, state, peek.shape(), u as i128)
         }
         ScalarType::U128 => {
             let u = *peek.get::<u128>().unwrap();
@@ -66,7 +66,7 @@ pub fn scalar_to_v8<'mem, 'facet, 'shape, 'scope>(
         }
         ScalarType::USize => {
             let u = *peek.get::<usize>().unwrap();
-            Ok(v8::BigInt::new_from_u64(scope, u as u64).into())
+            int64_to_v8(scope, state, peek.shape(), u as i128)
         }
         ScalarType::I8 => {
             let i = *peek.get::<i8>().unwrap();
@@ -82,7 +82,7 @@ pub fn scalar_to_v8<'mem, 'facet, 'shape, 'scope>(
         }
         ScalarType::I64 => {
             let i = *peek.get::<i64>().unwrap();
-            Ok(v8::BigInt::new_from_i64(scope, i).into())
+            int64_to_v8(scope, state, peek.shape(), i as i128)
         }
         ScalarType::I128 => {
             let i = *peek.get::<i128>().unwrap();
@@ -90,7 +90,7 @@ pub fn scalar_to_v8<'mem, 'facet, 'shape, 'scope>(
         }
         ScalarType::ISize => {
             let i = *peek.get::<isize>().unwrap();
-            Ok(v8::BigInt::new_from_i64(scope, i as i64).into())
+            int64_to_v8(scope, state, peek.shape(), i as i128)
         }
         ScalarType::SocketAddr => {
             let addr = peek.get::<core::net::SocketAddr>().unwrap().to_string();
@@ -124,11 +124,32 @@ pub fn scalar_to_v8<'mem, 'facet, 'shape, 'scope>(
     }
 }
 
+/// `Number.MAX_SAFE_INTEGER`
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+/// Marshal a 64-bit integer (widened to `i128` to cover both `i64` and `u64`)
+/// according to the `Int64Repr` in effect.
+fn int64_to_v8<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    state: &MarshalState<'_, 'scope, '_, '_>,
+    shape: &'shape Shape<'shape>,
+    value: i128,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
+    let is_safe = (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&value);
+    match state.options.int64_repr {
+        Int64Repr::NumberIfSafe | Int64Repr::Number if is_safe => {
+            Ok(v8::Number::new(scope, value as f64).into())
+        }
+        Int64Repr::Number => Err(Error::IntOverflow(shape)),
+        Int64Repr::BigInt | Int64Repr::NumberIfSafe => Ok(i128_to_bigint(scope, value).into()),
+    }
+}
+
 pub fn scalar_from_v8<'scope, 'partial, 'facet, 'shape>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
     match ScalarType::try_from_shape(shape)
@@ -143,10 +164,8 @@ pub fn scalar_from_v8<'scope, 'partial, 'facet, 'shape>(
             }
         }
         ScalarType::Bool => {
-            if value.is_true() {
-                partial.set(true).map_err(Into::into)
-            } else if value.is_false() {
-                partial.set(false).map_err(Into::into)
+            if value.is_boolean() || state.options.coercion == Coercion::Lenient {
+                partial.set(value.boolean_value(scope)).map_err(Into::into)
             } else {
                 Err(Error::unexpected(shape, value.type_repr()))
             }
@@ -167,10 +186,19 @@ pub fn scalar_from_v8<'scope, 'partial, 'facet, 'shape>(
             };
             partial.set(first_char).map_err(Into::into)
         }
-        ScalarType::Str => Err(ReflectError::OperationFailed {
-            shape,
-            operation: "cannot unmarshal string slices directly; use `String` or `Cow<str>` instead",
-        }.into()),
+        ScalarType::Str => {
+            let Some(arena) = state.arena() else {
+                return Err(ReflectError::OperationFailed {
+                    shape,
+                    operation: "cannot unmarshal string slices without an arena; use `from_v8_in()`, `String` or `Cow<str>` instead",
+                }
+                .into());
+            };
+            let s = string_from_v8(scope, value, partial.shape(), state)?;
+            let s: &str = arena.alloc_str(&s);
+            // SAFETY: The arena outlives the value being unmarshalled.
+            unsafe { partial.set_shape(PtrConst::new(&raw const s), shape) }.map_err(Into::into)
+        }
         ScalarType::String => {
             let s = string_from_v8(scope, value, partial.shape(), state)?;
             partial.set(s.into_owned()).map_err(Into::into)
@@ -180,29 +208,25 @@ pub fn scalar_from_v8<'scope, 'partial, 'facet, 'shape>(
             partial.set(Cow::Owned(s.into_owned())).map_err(Into::into)
         }
         ScalarType::F32 => {
-            let number = value
-                .to_number(scope)
-                .ok_or(Error::unexpected(shape, value.type_repr()))?;
-            partial.set(number.value() as f32).map_err(Into::into)
+            let number = number_from_v8(scope, value, shape, state)?;
+            partial.set(number as f32).map_err(Into::into)
         }
         ScalarType::F64 => {
-            let number = value
-                .to_number(scope)
-                .ok_or(Error::unexpected(shape, value.type_repr()))?;
-            partial.set(number.value()).map_err(Into::into)
-        }
-        ScalarType::U8 => unmarshal_via::<u8, u64>(value, partial),
-        ScalarType::U16 => unmarshal_via::<u16, u64>(value, partial),
-        ScalarType::U32 => unmarshal_via::<u32, u64>(value, partial),
-        ScalarType::U64 => unmarshal_via::<u64, u64>(value, partial),
-        ScalarType::U128 => unmarshal_via::<u128, u128>(value, partial),
-        ScalarType::USize => unmarshal_via::<usize, u64>(value, partial),
-        ScalarType::I8 => unmarshal_via::<i8, i64>(value, partial),
-        ScalarType::I16 => unmarshal_via::<i16, i64>(value, partial),
-        ScalarType::I32 => unmarshal_via::<i32, i64>(value, partial),
-        ScalarType::I64 => unmarshal_via::<i64, i64>(value, partial),
-        ScalarType::I128 => unmarshal_via::<i128, i128>(value, partial),
-        ScalarType::ISize => unmarshal_via::<isize, i64>(value, partial),
+            let number = number_from_v8(scope, value, shape, state)?;
+            partial.set(number).map_err(Into::into)
+        }
+        ScalarType::U8 => unmarshal_via::<u8, u64>(scope, value, partial, state),
+        ScalarType::U16 => unmarshal_via::<u16, u64>(scope, value, partial, state),
+        ScalarType::U32 => unmarshal_via::<u32, u64>(scope, value, partial, state),
+        ScalarType::U64 => unmarshal_via::<u64, u64>(scope, value, partial, state),
+        ScalarType::U128 => unmarshal_via::<u128, u128>(scope, value, partial, state),
+        ScalarType::USize => unmarshal_via::<usize, u64>(scope, value, partial, state),
+        ScalarType::I8 => unmarshal_via::<i8, i64>(scope, value, partial, state),
+        ScalarType::I16 => unmarshal_via::<i16, i64>(scope, value, partial, state),
+        ScalarType::I32 => unmarshal_via::<i32, i64>(scope, value, partial, state),
+        ScalarType::I64 => unmarshal_via::<i64, i64>(scope, value, partial, state),
+        ScalarType::I128 => unmarshal_via::<i128, i128>(scope, value, partial, state),
+        ScalarType::ISize => unmarshal_via::<isize, i64>(scope, value, partial, state),
         ScalarType::SocketAddr
         | ScalarType::IpAddr
         | ScalarType::Ipv4Addr
@@ -218,15 +242,102 @@ fn string_from_v8<'scope, 'shape, 'state>(
     scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     shape: &'shape Shape<'shape>,
-    state: &'state mut UnmarshalState<'_, 'scope>,
+    state: &'state mut UnmarshalState<'scope>,
 ) -> Result<Cow<'state, str>, Error<'shape>> {
-    if let Ok(s) = v8::Local::<v8::String>::try_from(value) {
-        Ok(s.to_rust_cow_lossy(scope, &mut state.string_conversion_buffer))
+    let s = match v8::Local::<v8::String>::try_from(value) {
+        Ok(s) => s,
+        Err(_)
+            if state.options.coercion == Coercion::Lenient
+                && (value.is_number() || value.is_big_int() || value.is_boolean()) =>
+        {
+            value.to_string(scope).ok_or_else(Error::exception)?
+        }
+        Err(_) => return Err(Error::unexpected(shape, value.type_repr())),
+    };
+    if let Some(max) = state.options.max_string_len {
+        // Every UTF-16 code unit is at least one UTF-8 byte, so long strings
+        // can be rejected without computing the UTF-8 length (reporting a
+        // lower bound).
+        let len = if s.length() > max {
+            s.length()
+        } else {
+            s.utf8_length(scope)
+        };
+        if len > max {
+            return Err(Error::StringTooLong { shape, len, max });
+        }
+    }
+    let string = s.to_rust_cow_lossy(scope, &mut state.string_conversion_buffer);
+    if state.options.strings == StringConversion::Strict
+        && string.contains(char::REPLACEMENT_CHARACTER)
+        && !is_well_formed(scope, s)
+    {
+        return Err(ReflectError::OperationFailed {
+            shape,
+            operation: "string contains unpaired surrogates",
+        }
+        .into());
+    }
+    Ok(string)
+}
+
+fn number_from_v8<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    shape: &'shape Shape<'shape>,
+    state: &UnmarshalState<'scope>,
+) -> Result<f64, Error<'shape>> {
+    let value = match state.options.coercion {
+        Coercion::Strict => value,
+        Coercion::Lenient => coerce_to_number(scope, value, shape)?,
+    };
+    v8::Local::<v8::Number>::try_from(value)
+        .map(|number| number.value())
+        .map_err(|_| Error::unexpected(shape, value.type_repr()))
+}
+
+/// Convert strings, booleans, `null` and `undefined` to a number like
+/// `Number(value)` does. Numbers and bigints are returned as-is, and objects are
+/// rejected, because converting them could run arbitrary JS code.
+fn coerce_to_number<'scope, 'shape>(
+    scope: &mut v8::HandleScope<'scope>,
+    value: v8::Local<'scope, v8::Value>,
+    shape: &'shape Shape<'shape>,
+) -> Result<v8::Local<'scope, v8::Value>, Error<'shape>> {
+    if value.is_number() || value.is_big_int() {
+        Ok(value)
+    } else if value.is_string() || value.is_boolean() || value.is_null_or_undefined() {
+        let number = value.to_number(scope).ok_or_else(Error::exception)?;
+        Ok(number.into())
     } else {
         Err(Error::unexpected(shape, value.type_repr()))
     }
 }
 
+/// Returns `true` if the string does not contain unpaired surrogates, meaning it
+/// can be converted to UTF-8 without loss.
+fn is_well_formed(scope: &mut v8::HandleScope, s: v8::Local<v8::String>) -> bool {
+    let mut buf = vec![0u16; s.length()];
+    s.write_v2(scope, 0, &mut buf, v8::WriteFlags::empty());
+    char::decode_utf16(buf).all(|c| c.is_ok())
+}
+
+/// Convert a JS number that is not a `v8::Integer` to an integer, if it is
+/// integral and within `Number.MAX_SAFE_INTEGER`, meaning no precision was lost
+/// on the JS side.
+fn safe_integer<'shape>(number: f64, shape: &'shape Shape<'shape>) -> Result<i64, Error<'shape>> {
+    if !number.is_finite() || number.fract() != 0.0 {
+        Err(Error::NonIntegralNumber {
+            shape,
+            value: number,
+        })
+    } else if number.abs() > MAX_SAFE_INTEGER as f64 {
+        Err(Error::IntOverflow(shape))
+    } else {
+        Ok(number as i64)
+    }
+}
+
 trait IntConversion: Sized {
     fn int_from_v8<'shape>(
         value: v8::Local<v8::Value>,
@@ -248,6 +359,8 @@ impl IntConversion for i64 {
             } else {
                 Ok(value)
             }
+        } else if let Ok(number) = v8::Local::<v8::Number>::try_from(value) {
+            safe_integer(number.value(), shape)
         } else {
             Err(Error::UnexpectedValue {
                 shape,
@@ -274,6 +387,10 @@ impl IntConversion for u64 {
             } else {
                 Err(Error::IntOverflow(shape))
             }
+        } else if let Ok(number) = v8::Local::<v8::Number>::try_from(value) {
+            safe_integer(number.value(), shape)?
+                .try_into()
+                .map_err(|_| Error::IntOverflow(shape))
         } else {
             Err(Error::UnexpectedValue {
                 shape,
@@ -292,6 +409,8 @@ impl IntConversion for i128 {
             Ok(number.value() as i128)
         } else if let Ok(bigint) = v8::Local::<v8::BigInt>::try_from(value) {
             bigint_to_i128(bigint).ok_or(Error::IntOverflow(shape))
+        } else if let Ok(number) = v8::Local::<v8::Number>::try_from(value) {
+            Ok(safe_integer(number.value(), shape)? as i128)
         } else {
             Err(Error::UnexpectedValue {
                 shape,
@@ -314,6 +433,11 @@ impl IntConversion for u128 {
             Ok(u as u128)
         } else if let Ok(bigint) = v8::Local::<v8::BigInt>::try_from(value) {
             bigint_to_u128(bigint).ok_or(Error::IntOverflow(shape))
+        } else if let Ok(number) = v8::Local::<v8::Number>::try_from(value) {
+            let u: u64 = safe_integer(number.value(), shape)?
+                .try_into()
+                .map_err(|_| Error::IntOverflow(shape))?;
+            Ok(u as u128)
         } else {
             Err(Error::UnexpectedValue {
                 shape,
@@ -331,10 +455,16 @@ fn unmarshal_via<
     T: facet_core::Facet<'facet> + TryFrom<U>,
     U: IntConversion,
 >(
+    scope: &mut v8::HandleScope<'scope>,
     value: v8::Local<'scope, v8::Value>,
     partial: &'partial mut Partial<'facet, 'shape>,
+    state: &UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
+    let value = match state.options.coercion {
+        Coercion::Strict => value,
+        Coercion::Lenient => coerce_to_number(scope, value, shape)?,
+    };
     let i: T = U::int_from_v8(value, shape)?
         .try_into()
         .map_err(|_| Error::IntOverflow(T::SHAPE))?;
@@ -17,9 +17,10 @@ pub fn marshal_set_into<'mem, 'facet: 'mem, 'shape: 'facet, 'scope>(
 
     let set =
         v8::Local::<v8::Set>::try_from(object).expect("object constructor did not create a set");
-    for item in peek.iter() {
-        let item_value = super::marshal_value(item, scope, state, None)?;
-        set.add(scope, item_value).ok_or(Error::Exception)?;
+    for (i, item) in peek.iter().enumerate() {
+        let item_value =
+            super::marshal_value(item, scope, state, None).map_err(|err| err.at_index(i))?;
+        set.add(scope, item_value).ok_or_else(Error::exception)?;
     }
     Ok(())
 }
@@ -28,7 +29,7 @@ pub fn unmarshal_set<'scope, 'partial, 'facet, 'shape: 'facet>(
     scope: &mut v8::HandleScope<'scope>,
     object: v8::Local<'scope, v8::Object>,
     partial: &'partial mut Partial<'facet, 'shape>,
-    state: &mut UnmarshalState<'_, 'scope>,
+    state: &mut UnmarshalState<'scope>,
 ) -> Result<&'partial mut Partial<'facet, 'shape>, Error<'shape>> {
     let shape = partial.shape();
     let set = v8::Local::<v8::Set>::try_from(object).map_err(|_| Error::UnexpectedValue {
@@ -36,11 +37,14 @@ pub fn unmarshal_set<'scope, 'partial, 'facet, 'shape: 'facet>(
         unexpected: object.type_repr(),
     })?;
 
+    state.check_entries(shape, set.size())?;
     let array = set.as_array(scope);
     partial.begin_list()?;
     for i in 0..array.length() {
-        let item = array.get_index(scope, i).ok_or(Error::Exception)?;
-        super::unmarshal_value(scope, item, partial.begin_list_item()?, state)?.end()?;
+        let item = array.get_index(scope, i).ok_or_else(Error::exception)?;
+        super::unmarshal_value(scope, item, partial.begin_list_item()?, state)
+            .map_err(|err| err.at_index(i as usize))?
+            .end()?;
     }
     // Note: `begin_list()` does not push a frame.
     Ok(partial)
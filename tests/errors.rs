@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+
+use facet::Facet;
+use facet_v8::{
+    Constructors, Error, ErrorKind, MapRepr, PropertyAccess, UnmarshalOptions, from_v8,
+    from_v8_with_options, to_v8, to_v8_with_constructors,
+};
+
+mod util;
+use util::{compile_function, eval, run};
+
+#[derive(Facet, Debug)]
+struct Owner {
+    id: u32,
+}
+
+#[derive(Facet, Debug)]
+struct Item {
+    owner: Owner,
+}
+
+#[derive(Facet, Debug)]
+struct Items {
+    items: Vec<Item>,
+}
+
+#[test]
+fn unmarshal_error_path() {
+    run(|scope| {
+        let value = eval(scope, "({ items: [{ owner: { id: 1 } }, { owner: { id: 'x' } }] })");
+        let err = from_v8::<Items>(scope, value).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "$.items[1].owner.id");
+        assert!(matches!(err.without_path(), Error::UnexpectedValue { .. }));
+        assert!(err.to_string().ends_with(" at $.items[1].owner.id"));
+
+        // Errors at the top level have no path.
+        let value = eval(scope, "'not a number'");
+        let err = from_v8::<u32>(scope, value).unwrap_err();
+        assert!(err.path().is_none());
+    })
+}
+
+#[derive(Facet, Debug)]
+#[repr(u8)]
+#[allow(dead_code)]
+enum Shape {
+    Circle { radius: f64 },
+    Polygon(Vec<(i32, i32)>),
+}
+
+#[test]
+fn unmarshal_error_path_in_enum() {
+    run(|scope| {
+        let value = eval(scope, "({ type: 'Polygon', 0: [[1, 2], [3, 'four']] })");
+        let err = from_v8::<Shape>(scope, value).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "$[0][1][1]");
+    })
+}
+
+#[derive(Facet)]
+#[repr(u8)]
+#[allow(dead_code)]
+enum Clobbering {
+    Variant {
+        #[facet(rename = "type")]
+        kind: u32,
+    },
+}
+
+#[derive(Facet)]
+struct Outer {
+    inner: Vec<Clobbering>,
+}
+
+#[test]
+fn marshal_error_path() {
+    run(|scope| {
+        let err = to_v8(
+            scope,
+            &Outer {
+                inner: vec![Clobbering::Variant { kind: 1 }],
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "$.inner[0]");
+        assert!(matches!(err.without_path(), Error::ClobberedTypeTag(_)));
+    })
+}
+
+#[test]
+fn mismatched_constructor() {
+    run(|scope| {
+        // A typed array of the wrong type is an error rather than a panic.
+        let constructor = compile_function(
+            scope,
+            "Bytes",
+            "function Bytes() { return new Uint8Array(2); }",
+        );
+        let err = to_v8_with_constructors(
+            scope,
+            &vec![1u16, 2],
+            Constructors::default().with_constructor::<Vec<u16>>(constructor),
+        )
+        .unwrap_err();
+        assert!(matches!(err.without_path(), Error::Reflect(_)));
+
+        // So is an object that is neither an array nor a typed array.
+        let constructor = compile_function(scope, "Plain", "function Plain() {}");
+        let err = to_v8_with_constructors(
+            scope,
+            &vec![1u16, 2],
+            Constructors::default().with_constructor::<Vec<u16>>(constructor),
+        )
+        .unwrap_err();
+        assert!(matches!(err.without_path(), Error::Reflect(_)));
+    })
+}
+
+#[test]
+fn exception_is_captured() {
+    run(|scope| {
+        let constructor = compile_function(
+            scope,
+            "Throwing",
+            "function Throwing() { throw new Error('constructor failed'); }",
+        );
+        let err = to_v8_with_constructors(
+            scope,
+            &Owner { id: 1 },
+            Constructors::default().with_constructor::<Owner>(constructor),
+        )
+        .unwrap_err();
+        let Error::Exception(exception) = err.without_path() else {
+            panic!("expected an exception, got {err}");
+        };
+        assert!(exception.message.as_ref().unwrap().contains("constructor failed"));
+        assert!(exception.stack_trace.as_ref().unwrap().contains("Throwing"));
+        let thrown = v8::Local::new(scope, exception.value.as_ref().unwrap());
+        assert!(thrown.is_native_error());
+
+        // Getters that throw are reported the same way.
+        let value = eval(scope, "({ get id() { throw new RangeError('getter failed'); } })");
+        let err = from_v8::<Owner>(scope, value).unwrap_err();
+        assert!(err.to_string().contains("getter failed"));
+        assert!(err.without_path().to_string().starts_with("JavaScript exception: "));
+    })
+}
+
+#[test]
+fn throw_error_into_js() {
+    run(|scope| {
+        let value = eval(scope, "({ items: [{ owner: { id: -1 } }] })");
+        let err = from_v8::<Items>(scope, value).unwrap_err();
+        let js_error = v8::Local::<v8::Object>::try_from(err.to_js_error(scope)).unwrap();
+        util::check_function(
+            scope,
+            "check",
+            &[js_error.into()],
+            r#"function check(err) {
+                if (!(err instanceof RangeError)) {
+                    throw new Error(`expected RangeError, got ${err}`);
+                }
+                if (err.path !== '$.items[0].owner.id' || err.shape !== 'u32') {
+                    throw new Error(`unexpected path or shape: ${err.path}, ${err.shape}`);
+                }
+                if (typeof err.cause !== 'string') {
+                    throw new Error('expected a cause');
+                }
+            }"#,
+        );
+
+        let value = eval(scope, "({ items: 'nope' })");
+        let err = from_v8::<Items>(scope, value).unwrap_err();
+        let type_error = eval(scope, "TypeError");
+        let scope = &mut v8::TryCatch::new(scope);
+        err.throw(scope);
+        let thrown = scope.exception().unwrap();
+        let thrown = v8::Local::<v8::Object>::try_from(thrown).unwrap();
+        assert!(
+            thrown
+                .instance_of(scope, type_error.try_into().unwrap())
+                .unwrap()
+        );
+    })
+}
+
+#[test]
+fn owned_error() {
+    fn assert_send_sync_static<T: Send + Sync + 'static>(_: &T) {}
+
+    run(|scope| {
+        let value = eval(scope, "({ items: [{ owner: { id: 'x' } }] })");
+        let err = from_v8::<Items>(scope, value).unwrap_err();
+        let message = err.to_string();
+        let owned = err.into_owned();
+        assert_send_sync_static(&owned);
+        assert_eq!(owned.kind(), ErrorKind::UnexpectedValue);
+        assert_eq!(owned.shape(), Some("u32"));
+        assert_eq!(owned.path().unwrap().to_string(), "$.items[0].owner.id");
+        assert_eq!(owned.to_string(), message);
+
+        let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(owned);
+        assert_eq!(boxed.to_string(), message);
+    })
+}
+
+#[derive(Facet, Debug)]
+struct Node {
+    value: u32,
+    next: Option<Box<Node>>,
+}
+
+#[test]
+fn cycle_is_detected() {
+    run(|scope| {
+        let value = eval(scope, "const a = { value: 1, next: { value: 2 } }; a.next.next = a; a");
+        let err = from_v8::<Node>(scope, value).unwrap_err();
+        assert!(matches!(err.without_path(), Error::Cycle(_)));
+        assert_eq!(err.path().unwrap().to_string(), "$.next.next");
+
+        // The same object may appear several times, as long as it does not
+        // contain itself.
+        let value = eval(scope, "const leaf = { value: 1 }; [leaf, leaf]");
+        let nodes = from_v8::<Vec<Node>>(scope, value).unwrap();
+        assert_eq!(nodes.len(), 2);
+    })
+}
+
+#[test]
+fn depth_limit() {
+    run(|scope| {
+        let value = eval(
+            scope,
+            "let deep = { value: 0 }; for (let i = 1; i < 300; i++) deep = { value: i, next: deep }; deep",
+        );
+        let err = from_v8::<Node>(scope, value).unwrap_err();
+        assert!(matches!(
+            err.without_path(),
+            Error::DepthLimitExceeded { max_depth: 128, .. }
+        ));
+
+        let options = UnmarshalOptions {
+            max_depth: 300,
+            ..Default::default()
+        };
+        let node = from_v8_with_options::<Node>(scope, value, &options).unwrap();
+        assert_eq!(node.value, 299);
+    })
+}
+
+#[test]
+fn resource_limits() {
+    run(|scope| {
+        let options = UnmarshalOptions {
+            max_array_len: Some(10),
+            max_string_len: Some(10),
+            max_entries: Some(10),
+            max_nodes: Some(100),
+            ..Default::default()
+        };
+
+        let value = eval(scope, "new Array(1e8)");
+        let err = from_v8_with_options::<Vec<u32>>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::ArrayTooLong { len: 100_000_000, max: 10, .. }));
+
+        let value = eval(scope, "new Uint8Array(11)");
+        let err = from_v8_with_options::<Vec<u8>>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::ArrayTooLong { len: 11, .. }));
+
+        let value = eval(scope, "'\\u00e9'.repeat(6)");
+        let err = from_v8_with_options::<String>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::StringTooLong { len: 12, .. }));
+
+        let value = eval(scope, "new Map(Array.from({ length: 11 }, (_, i) => [i, i]))");
+        let err = from_v8_with_options::<HashMap<u32, u32>>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::TooManyEntries { len: 11, .. }));
+
+        let value = eval(scope, "new Set(Array.from({ length: 11 }, (_, i) => i))");
+        let err = from_v8_with_options::<HashSet<u32>>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::TooManyEntries { len: 11, .. }));
+
+        let value = eval(scope, "Array.from({ length: 10 }, () => Array(10).fill(0))");
+        let err = from_v8_with_options::<Vec<Vec<u32>>>(scope, value, &options).unwrap_err();
+        assert!(matches!(err.without_path(), Error::TooManyNodes { max: 100, .. }));
+
+        // Within the limits.
+        let value = eval(scope, "['short', 'strings']");
+        let strings = from_v8_with_options::<Vec<String>>(scope, value, &options).unwrap();
+        assert_eq!(strings, ["short", "strings"]);
+    })
+}
+
+#[test]
+fn own_data_properties_only() {
+    run(|scope| {
+        let options = UnmarshalOptions {
+            property_access: PropertyAccess::OwnDataOnly,
+            ..Default::default()
+        };
+
+        // Getters are rejected without being invoked.
+        let value = eval(
+            scope,
+            "globalThis.called = false; ({ get id() { globalThis.called = true; return 1; } })",
+        );
+        let err = from_v8_with_options::<Owner>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::AccessorProperty { ref property, .. } if property == "id"));
+        assert_eq!(err.kind(), ErrorKind::AccessorProperty);
+        assert!(eval(scope, "globalThis.called").is_false());
+
+        // Unknown properties are skipped without being read, even if they are
+        // accessors.
+        let value = eval(
+            scope,
+            "({ id: 1, get extra() { globalThis.called = true; return 2; } })",
+        );
+        let owner = from_v8_with_options::<Owner>(scope, value, &options).unwrap();
+        assert_eq!(owner.id, 1);
+        assert!(eval(scope, "globalThis.called").is_false());
+
+        // Getters on array elements, too.
+        let value = eval(scope, "Object.defineProperty([1, 2], 1, { get() { return 3; } })");
+        let err = from_v8_with_options::<Vec<u32>>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::AccessorProperty { .. }));
+
+        let value = eval(scope, "new Proxy({ id: 1 }, {})");
+        let err = from_v8_with_options::<Owner>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::Proxy(_)));
+
+        // Inherited properties are not read.
+        let value = eval(scope, "Object.create({ id: 1 })");
+        assert!(from_v8_with_options::<Owner>(scope, value, &options).is_err());
+
+        // `__proto__` is an ordinary key.
+        let value = eval(scope, "JSON.parse('{ \"id\": 1, \"__proto__\": { \"id\": 2 } }')");
+        let owner = from_v8_with_options::<Owner>(scope, value, &options).unwrap();
+        assert_eq!(owner.id, 1);
+        let value = eval(scope, "JSON.parse('{ \"__proto__\": 2 }')");
+        let map = from_v8_with_options::<HashMap<String, u32>>(
+            scope,
+            value,
+            &UnmarshalOptions {
+                map_repr: MapRepr::Object,
+                ..options
+            },
+        )
+        .unwrap();
+        assert_eq!(map["__proto__"], 2);
+
+        // Getters run by default.
+        let value = eval(scope, "({ get id() { return 1; } })");
+        assert_eq!(from_v8::<Owner>(scope, value).unwrap().id, 1);
+    })
+}
@@ -0,0 +1,493 @@
+use std::collections::{BTreeMap, HashMap};
+
+use facet::Facet;
+use facet_v8::{
+    Coercion, Constructors, Error, Int64Repr, MapRepr, MarshalOptions, PropertyWrite,
+    StringConversion, TypedArrayConversion, UnknownFields, UnmarshalOptions, from_v8,
+    from_v8_into, from_v8_into_with_options, from_v8_with_options, to_v8,
+    to_v8_into_with_options, to_v8_with_options,
+};
+
+mod util;
+use util::{compile_function, eval, run};
+
+#[derive(Facet, Debug, PartialEq)]
+struct Scores {
+    by_name: BTreeMap<String, u32>,
+    by_id: HashMap<u32, String>,
+}
+
+#[test]
+fn maps_as_objects() {
+    run(|scope| {
+        let scores = Scores {
+            by_name: BTreeMap::from([("alice".to_string(), 3), ("bob".to_string(), 5)]),
+            by_id: HashMap::from([(1, "alice".to_string()), (2, "bob".to_string())]),
+        };
+        let options = MarshalOptions {
+            map_repr: MapRepr::Object,
+            ..Default::default()
+        };
+        let value =
+            to_v8_with_options(scope, &scores, &options, &mut Constructors::default()).unwrap();
+
+        let check = compile_function(
+            scope,
+            "check",
+            r#"
+            function check(scores) {
+                if (scores.by_name instanceof Map) throw new Error("expected an object");
+                if (scores.by_name.alice !== 3) throw new Error("alice");
+                if (scores.by_name.bob !== 5) throw new Error("bob");
+                if (scores.by_id[1] !== "alice") throw new Error("1");
+                if (scores.by_id[2] !== "bob") throw new Error("2");
+            }
+            "#,
+        );
+        let undefined = v8::undefined(scope).into();
+        check.call(scope, undefined, &[value]).unwrap();
+
+        // Objects are not accepted as maps by default.
+        assert!(from_v8::<Scores>(scope, value).is_err());
+
+        let options = UnmarshalOptions {
+            map_repr: MapRepr::Object,
+            ..Default::default()
+        };
+        let round_trip: Scores = from_v8_with_options(scope, value, &options).unwrap();
+        assert_eq!(round_trip, scores);
+    })
+}
+
+#[derive(Facet, Debug, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[test]
+fn deny_unknown_fields() {
+    run(|scope| {
+        let value = eval(scope, "({ x: 1, y: 2, z: 3 })");
+        let point: Point = from_v8(scope, value).unwrap();
+        assert_eq!(point, Point { x: 1, y: 2 });
+
+        let options = UnmarshalOptions {
+            unknown_fields: UnknownFields::Deny,
+            ..Default::default()
+        };
+        let err = from_v8_with_options::<Point>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::UnknownField { ref field, .. } if field == "z"));
+    })
+}
+
+#[derive(Facet, Debug, PartialEq)]
+struct Line {
+    from: Point,
+    to: Point,
+}
+
+#[test]
+fn deny_unknown_fields_when_patching() {
+    run(|scope| {
+        let mut line = Line {
+            from: Point { x: 0, y: 0 },
+            to: Point { x: 1, y: 1 },
+        };
+        let value = eval(scope, "({ from: { x: 5, z: 3 } })");
+        let options = UnmarshalOptions {
+            unknown_fields: UnknownFields::Deny,
+            ..Default::default()
+        };
+        let err = from_v8_into_with_options(scope, value, &mut line, &options).unwrap_err();
+        assert!(matches!(err.without_path(), Error::UnknownField { field, .. } if field == "z"));
+        assert_eq!(err.path().unwrap().to_string(), "$.from");
+
+        from_v8_into(scope, value, &mut line).unwrap();
+        assert_eq!(line.from, Point { x: 5, y: 0 });
+        assert_eq!(line.to, Point { x: 1, y: 1 });
+
+        // Patches count towards the same limits as new values.
+        let options = UnmarshalOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let err = from_v8_into_with_options(scope, value, &mut line, &options).unwrap_err();
+        assert!(matches!(
+            err.without_path(),
+            Error::DepthLimitExceeded { max_depth: 1, .. }
+        ));
+    })
+}
+
+#[derive(Facet, Debug, PartialEq)]
+#[repr(u8)]
+enum Command {
+    Move { x: i32, y: i32 },
+}
+
+#[test]
+fn deny_unknown_fields_in_enum_variants() {
+    run(|scope| {
+        let value = eval(scope, "({ type: 'Move', x: 1, y: 2, z: 3 })");
+        let command: Command = from_v8(scope, value).unwrap();
+        assert_eq!(command, Command::Move { x: 1, y: 2 });
+
+        let options = UnmarshalOptions {
+            unknown_fields: UnknownFields::Deny,
+            ..Default::default()
+        };
+        let err = from_v8_with_options::<Command>(scope, value, &options).unwrap_err();
+        assert!(matches!(err, Error::UnknownField { ref field, .. } if field == "z"));
+
+        // The tag is not an unknown field.
+        let value = eval(scope, "({ type: 'Move', x: 1, y: 2 })");
+        let command: Command = from_v8_with_options(scope, value, &options).unwrap();
+        assert_eq!(command, Command::Move { x: 1, y: 2 });
+    })
+}
+
+#[test]
+fn strict_strings() {
+    run(|scope| {
+        let value = eval(scope, "'abc\\uD800def'");
+        let s: String = from_v8(scope, value).unwrap();
+        assert_eq!(s, "abc\u{FFFD}def");
+
+        let options = UnmarshalOptions {
+            strings: StringConversion::Strict,
+            ..Default::default()
+        };
+        assert!(from_v8_with_options::<String>(scope, value, &options).is_err());
+
+        // Replacement characters that are actually in the string are fine.
+        let value = eval(scope, "'abc\\uFFFDdef'");
+        let s: String = from_v8_with_options(scope, value, &options).unwrap();
+        assert_eq!(s, "abc\u{FFFD}def");
+    })
+}
+
+#[derive(Facet)]
+struct Ids {
+    plain: u64,
+    #[facet(js_number)]
+    numbers: Vec<u64>,
+    #[facet(js_number)]
+    by_id: BTreeMap<i64, String>,
+}
+
+#[test]
+fn int64_as_number() {
+    run(|scope| {
+        let ids = Ids {
+            plain: 1,
+            numbers: vec![1, 2, (1 << 53) - 1],
+            by_id: BTreeMap::from([(-3, "a".to_string())]),
+        };
+        let value = to_v8_with_options(
+            scope,
+            &ids,
+            &MarshalOptions::default(),
+            &mut Constructors::default(),
+        )
+        .unwrap();
+        let check = compile_function(
+            scope,
+            "check",
+            r#"
+            function check(ids) {
+                if (ids.plain !== 1n) throw new Error("plain");
+                if (ids.numbers[1] + 1 !== 3) throw new Error("numbers");
+                if (ids.numbers[2] !== Number.MAX_SAFE_INTEGER) throw new Error("max");
+                if (ids.by_id.get(-3) !== "a") throw new Error("by_id");
+            }
+            "#,
+        );
+        let undefined = v8::undefined(scope).into();
+        check.call(scope, undefined, &[value]).unwrap();
+
+        // Unsafe integers are an error in `js_number` fields.
+        let ids = Ids {
+            plain: 1,
+            numbers: vec![1 << 53],
+            by_id: BTreeMap::new(),
+        };
+        let err = to_v8_with_options(
+            scope,
+            &ids,
+            &MarshalOptions::default(),
+            &mut Constructors::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err.without_path(), Error::IntOverflow(_)));
+        assert_eq!(err.path().unwrap().to_string(), "$.numbers[0]");
+    })
+}
+
+#[test]
+fn int64_repr_policy() {
+    run(|scope| {
+        let values: Vec<i64> = vec![-5, 1 << 53, i64::MIN];
+        let options = MarshalOptions {
+            int64_repr: Int64Repr::NumberIfSafe,
+            ..Default::default()
+        };
+        let value =
+            to_v8_with_options(scope, &values, &options, &mut Constructors::default()).unwrap();
+        let check = compile_function(
+            scope,
+            "check",
+            r#"
+            function check(values) {
+                if (values[0] !== -5) throw new Error("safe");
+                if (values[1] !== 2n ** 53n) throw new Error("unsafe");
+                if (values[2] !== -(2n ** 63n)) throw new Error("min");
+            }
+            "#,
+        );
+        let undefined = v8::undefined(scope).into();
+        check.call(scope, undefined, &[value]).unwrap();
+
+        let options = MarshalOptions {
+            int64_repr: Int64Repr::Number,
+            ..Default::default()
+        };
+        let err =
+            to_v8_with_options(scope, &values, &options, &mut Constructors::default()).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "$[1]");
+    })
+}
+
+#[derive(Facet, Debug, PartialEq)]
+struct Form {
+    name: String,
+    #[facet(js_lenient)]
+    age: u32,
+    #[facet(js_lenient)]
+    score: f64,
+    #[facet(js_lenient)]
+    subscribed: bool,
+}
+
+#[test]
+fn coercion() {
+    run(|scope| {
+        // Floats only accept numbers by default.
+        let value = eval(scope, "'12'");
+        assert!(from_v8::<f64>(scope, value).is_err());
+        let value = eval(scope, "true");
+        assert!(from_v8::<f64>(scope, value).is_err());
+        let value = eval(scope, "[]");
+        assert!(from_v8::<f64>(scope, value).is_err());
+        assert!(from_v8::<f32>(scope, value).is_err());
+        let value = eval(scope, "null");
+        assert!(from_v8::<f32>(scope, value).is_err());
+        let value = eval(scope, "1.5");
+        assert_eq!(from_v8::<f32>(scope, value).unwrap(), 1.5);
+
+        let lenient = UnmarshalOptions {
+            coercion: Coercion::Lenient,
+            ..Default::default()
+        };
+        let value = eval(scope, "'12'");
+        assert_eq!(from_v8_with_options::<f64>(scope, value, &lenient).unwrap(), 12.0);
+        assert_eq!(from_v8_with_options::<u8>(scope, value, &lenient).unwrap(), 12);
+        let value = eval(scope, "'1.5'");
+        assert!(matches!(
+            from_v8_with_options::<u8>(scope, value, &lenient),
+            Err(Error::NonIntegralNumber { .. })
+        ));
+        let value = eval(scope, "12");
+        assert_eq!(from_v8_with_options::<String>(scope, value, &lenient).unwrap(), "12");
+        let value = eval(scope, "''");
+        assert!(!from_v8_with_options::<bool>(scope, value, &lenient).unwrap());
+        let value = eval(scope, "({})");
+        assert!(from_v8_with_options::<bool>(scope, value, &lenient).unwrap());
+        // Objects are not converted to numbers or strings.
+        assert!(from_v8_with_options::<f64>(scope, value, &lenient).is_err());
+        let value = eval(scope, "[]");
+        assert!(from_v8_with_options::<f64>(scope, value, &lenient).is_err());
+        let value = eval(scope, "true");
+        assert_eq!(from_v8_with_options::<f32>(scope, value, &lenient).unwrap(), 1.0);
+        assert!(from_v8_with_options::<String>(scope, value, &lenient).is_err());
+    })
+}
+
+#[test]
+fn coercion_per_field() {
+    run(|scope| {
+        let value = eval(scope, "({ name: 'Ada', age: '36', score: '9.5', subscribed: 1 })");
+        let form: Form = from_v8(scope, value).unwrap();
+        assert_eq!(
+            form,
+            Form {
+                name: "Ada".to_string(),
+                age: 36,
+                score: 9.5,
+                subscribed: true,
+            }
+        );
+
+        // Fields without the attribute are still strict.
+        let value = eval(scope, "({ name: 1, age: 36, score: 9.5, subscribed: true })");
+        let err = from_v8::<Form>(scope, value).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "$.name");
+    })
+}
+
+#[derive(Facet, Debug, PartialEq)]
+struct Counter {
+    count: u64,
+}
+
+#[test]
+fn patch_with_options() {
+    run(|scope| {
+        // Field attributes apply when patching.
+        let mut form = Form {
+            name: "Ada".to_string(),
+            age: 36,
+            score: 9.5,
+            subscribed: false,
+        };
+        let value = eval(scope, "({ age: '37', subscribed: 1 })");
+        from_v8_into(scope, value, &mut form).unwrap();
+        assert_eq!(form.age, 37);
+        assert!(form.subscribed);
+
+        // Options apply to the other fields.
+        let value = eval(scope, "({ name: 42 })");
+        assert!(from_v8_into(scope, value, &mut form).is_err());
+        let lenient = UnmarshalOptions {
+            coercion: Coercion::Lenient,
+            ..Default::default()
+        };
+        from_v8_into_with_options(scope, value, &mut form, &lenient).unwrap();
+        assert_eq!(form.name, "42");
+
+        let object = eval(scope, "({ count: 0n })");
+        let object = v8::Local::<v8::Object>::try_from(object).unwrap();
+        let options = MarshalOptions {
+            int64_repr: Int64Repr::Number,
+            ..Default::default()
+        };
+        to_v8_into_with_options(
+            scope,
+            &Counter { count: 3 },
+            object,
+            &options,
+            &mut Constructors::default(),
+        )
+        .unwrap();
+        let key = v8::String::new(scope, "count").unwrap();
+        let count = object.get(scope, key.into()).unwrap();
+        assert!(count.is_number());
+        assert_eq!(from_v8::<Counter>(scope, object.into()).unwrap(), Counter { count: 3 });
+    })
+}
+
+#[derive(Facet)]
+struct Named {
+    name: String,
+    tags: Vec<u32>,
+}
+
+#[test]
+fn property_write() {
+    run(|scope| {
+        eval(
+            scope,
+            r#"
+            globalThis.hijacked = [];
+            Object.defineProperty(Object.prototype, "name", {
+                set(value) { hijacked.push(value); },
+            });
+            Object.defineProperty(Object.prototype, "__proto__", {
+                set(value) { hijacked.push(value); },
+            });
+            Object.defineProperty(Array.prototype, 0, {
+                set(value) { hijacked.push(value); },
+            });
+            "#,
+        );
+        let check = compile_function(
+            scope,
+            "check",
+            r#"
+            function check(named, map) {
+                if (hijacked.length !== 0) throw new Error("setter invoked");
+                if (!Object.hasOwn(named, "name")) throw new Error("name");
+                if (!Object.hasOwn(named.tags, 0)) throw new Error("tags");
+                if (!Object.hasOwn(map, "__proto__")) throw new Error("__proto__");
+            }
+            "#,
+        );
+
+        // Plain objects get data properties by default.
+        let named = Named {
+            name: "Ada".to_string(),
+            tags: vec![1],
+        };
+        let map = HashMap::from([("__proto__".to_string(), 1)]);
+        let options = MarshalOptions {
+            map_repr: MapRepr::Object,
+            ..Default::default()
+        };
+        let mut constructors = Constructors::default();
+        let named_value = to_v8(scope, &named).unwrap();
+        let map_value = to_v8_with_options(scope, &map, &options, &mut constructors).unwrap();
+        let undefined = v8::undefined(scope).into();
+        check.call(scope, undefined, &[named_value, map_value]).unwrap();
+
+        // Assigning invokes the setters.
+        let options = MarshalOptions {
+            property_write: PropertyWrite::Set,
+            ..Default::default()
+        };
+        to_v8_with_options(scope, &named, &options, &mut constructors).unwrap();
+        let hijacked = eval(scope, "hijacked.join()");
+        assert_eq!(hijacked.to_rust_string_lossy(scope), "Ada,1");
+    })
+}
+
+#[test]
+fn typed_array_conversion() {
+    run(|scope| {
+        // Untyped buffers are accepted as bytes.
+        let value = eval(scope, "new Uint8Array([1, 2, 3]).buffer");
+        assert_eq!(from_v8::<Vec<u8>>(scope, value).unwrap(), [1, 2, 3]);
+        let value = eval(scope, "new DataView(new Uint8Array([1, 2, 3, 4]).buffer, 1, 2)");
+        assert_eq!(from_v8::<Vec<u8>>(scope, value).unwrap(), [2, 3]);
+        let value = eval(scope, "new SharedArrayBuffer(2)");
+        assert_eq!(from_v8::<Vec<i8>>(scope, value).unwrap(), [0, 0]);
+        // Views only cover part of their buffer.
+        let value = eval(scope, "new Uint16Array([1, 2, 3]).subarray(1)");
+        assert_eq!(from_v8::<Vec<u16>>(scope, value).unwrap(), [2, 3]);
+
+        // Other element types must match exactly by default.
+        let value = eval(scope, "new Uint8Array(new Uint32Array([1, 0x01020304]).buffer)");
+        assert!(from_v8::<Vec<u32>>(scope, value).is_err());
+
+        let reinterpret = UnmarshalOptions {
+            typed_arrays: TypedArrayConversion::Reinterpret,
+            ..Default::default()
+        };
+        let words = from_v8_with_options::<Vec<u32>>(scope, value, &reinterpret).unwrap();
+        assert_eq!(words, [1, 0x01020304]);
+        let value = eval(scope, "new Float32Array([1.5]).buffer");
+        let floats = from_v8_with_options::<Vec<f32>>(scope, value, &reinterpret).unwrap();
+        assert_eq!(floats, [1.5]);
+        // Shared memory is copied too.
+        let value = eval(scope, "new Uint16Array(new SharedArrayBuffer(4)).fill(7)");
+        assert_eq!(from_v8::<Vec<u16>>(scope, value).unwrap(), [7, 7]);
+        let words = from_v8_with_options::<Vec<u32>>(scope, value, &reinterpret).unwrap();
+        assert_eq!(words, [0x00070007]);
+
+        // The length and offset must be multiples of the element size.
+        let value = eval(scope, "new Uint8Array(6)");
+        let err = from_v8_with_options::<Vec<u32>>(scope, value, &reinterpret).unwrap_err();
+        assert!(matches!(err, Error::Reflect(_)));
+        let value = eval(scope, "new DataView(new ArrayBuffer(8), 2, 4)");
+        assert!(from_v8_with_options::<Vec<u32>>(scope, value, &reinterpret).is_err());
+    })
+}
@@ -1,10 +1,15 @@
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
 
 use facet::Facet;
-use facet_v8::{Constructors, from_v8, to_v8, to_v8_with_constructors};
+use facet_v8::{
+    Arena, Constructors, Error, MarshalOptions, TypedArrayConversion, UnknownFields,
+    UnmarshalOptions, V8Buffer, from_v8, from_v8_in, from_v8_in_with_options, from_v8_into,
+    from_v8_with_options, to_v8, to_v8_into, to_v8_owned, to_v8_with_constructors,
+};
 
 mod util;
-use util::{check_function, compile_function, run};
+use util::{check_function, compile_function, eval, run};
 
 #[test]
 fn scalar() {
@@ -46,6 +51,33 @@ fn scalar() {
     });
 }
 
+#[test]
+fn large_numbers() {
+    run(|scope| {
+        let number = v8::Number::new(scope, 2f64.powi(40)).into();
+        assert_eq!(from_v8::<u64>(scope, number).unwrap(), 1 << 40);
+        assert_eq!(from_v8::<i64>(scope, number).unwrap(), 1 << 40);
+        assert_eq!(from_v8::<u128>(scope, number).unwrap(), 1 << 40);
+        assert_eq!(from_v8::<i128>(scope, number).unwrap(), 1 << 40);
+        assert!(matches!(from_v8::<u32>(scope, number), Err(Error::IntOverflow(_))));
+
+        let negative = v8::Number::new(scope, -(2f64.powi(40))).into();
+        assert_eq!(from_v8::<i64>(scope, negative).unwrap(), -(1 << 40));
+        assert!(matches!(from_v8::<u64>(scope, negative), Err(Error::IntOverflow(_))));
+
+        let max_safe = v8::Number::new(scope, 2f64.powi(53) - 1.0).into();
+        assert_eq!(from_v8::<u64>(scope, max_safe).unwrap(), (1 << 53) - 1);
+        let unsafe_ = v8::Number::new(scope, 2f64.powi(53)).into();
+        assert!(matches!(from_v8::<u64>(scope, unsafe_), Err(Error::IntOverflow(_))));
+
+        for invalid in [1.5, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let number = v8::Number::new(scope, invalid).into();
+            assert!(matches!(from_v8::<i64>(scope, number), Err(Error::NonIntegralNumber { .. })));
+            assert!(matches!(from_v8::<u8>(scope, number), Err(Error::NonIntegralNumber { .. })));
+        }
+    });
+}
+
 #[test]
 fn string() {
     run(|scope| {
@@ -239,6 +271,122 @@ struct PlainRcs {
     b: Rc<Plain>,
 }
 
+#[derive(Facet, Debug, Clone, PartialEq)]
+struct Audio {
+    volume: f64,
+    muted: bool,
+}
+
+#[derive(Facet, Debug, Clone, PartialEq)]
+struct Config {
+    name: String,
+    audio: Audio,
+    plugins: Vec<String>,
+    theme: Option<String>,
+}
+
+#[test]
+fn patch_in_place() {
+    run(|scope| {
+        let mut config = Config {
+            name: "default".to_string(),
+            audio: Audio {
+                volume: 1.0,
+                muted: false,
+            },
+            plugins: vec!["a".to_string(), "b".to_string()],
+            theme: Some("dark".to_string()),
+        };
+
+        let patch = compile_function(
+            scope,
+            "patch",
+            "function patch() { return { audio: { volume: 0.5 }, plugins: ['c'], theme: null }; }",
+        );
+        let undefined = v8::undefined(scope).into();
+        let value = patch.call(scope, undefined, &[]).unwrap();
+        from_v8_into(scope, value, &mut config).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "default".to_string(),
+                audio: Audio {
+                    volume: 0.5,
+                    muted: false,
+                },
+                plugins: vec!["c".to_string()],
+                theme: None,
+            }
+        );
+
+        // Errors report the path, and earlier fields keep their new values.
+        let patch = compile_function(
+            scope,
+            "patch",
+            "function patch() { return { name: 'custom', audio: { muted: 'yes' } }; }",
+        );
+        let value = patch.call(scope, undefined, &[]).unwrap();
+        let err = from_v8_into(scope, value, &mut config).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "$.audio.muted");
+        assert_eq!(config.name, "custom");
+        assert!(!config.audio.muted);
+    });
+}
+
+#[test]
+fn marshal_in_place() {
+    run(|scope| {
+        let mut config = Config {
+            name: "default".to_string(),
+            audio: Audio {
+                volume: 1.0,
+                muted: false,
+            },
+            plugins: vec!["a".to_string(), "b".to_string()],
+            theme: Some("dark".to_string()),
+        };
+        let value = to_v8(scope, &config).unwrap();
+        let object = v8::Local::<v8::Object>::try_from(value).unwrap();
+
+        let observe = compile_function(
+            scope,
+            "observe",
+            r#"
+            function observe(config) {
+                globalThis.observed = { config, audio: config.audio, plugins: config.plugins };
+            }
+            "#,
+        );
+        let undefined = v8::undefined(scope).into();
+        observe.call(scope, undefined, &[value]).unwrap();
+
+        config.audio.volume = 0.5;
+        config.plugins.pop();
+        config.theme = None;
+        to_v8_into(scope, &config, object).unwrap();
+
+        let check = compile_function(
+            scope,
+            "check",
+            r#"
+            function check(config) {
+                const { observed } = globalThis;
+                if (observed.config !== config) throw new Error("config identity");
+                if (observed.audio !== config.audio) throw new Error("audio identity");
+                if (observed.plugins !== config.plugins) throw new Error("plugins identity");
+                if (config.audio.volume !== 0.5) throw new Error("volume");
+                if (config.plugins.length !== 1) throw new Error("plugins");
+                if ("theme" in config) throw new Error("theme");
+            }
+            "#,
+        );
+        check.call(scope, undefined, &[value]).unwrap();
+
+        // Values that don't marshal as objects cannot be marshalled in place.
+        assert!(to_v8_into(scope, &1u32, object).is_err());
+    });
+}
+
 #[test]
 fn smart_pointers() {
     run(|scope| {
@@ -253,12 +401,13 @@ fn smart_pointers() {
                 a: plain.clone(),
                 b: plain,
             },
-        );
+        )
+        .unwrap();
         // Check that `a` and `b` are the same object.
         check_function(
             scope,
             "check",
-            &[plain_rcs.unwrap()],
+            &[plain_rcs],
             r#"function check(obj) {
                 if (typeof obj !== 'object') {
                     throw new Error('expected object');
@@ -271,6 +420,232 @@ fn smart_pointers() {
                 }
             }"#,
         );
+
+        // Check that `a` and `b` are still the same object when coming back.
+        let plain_rcs = from_v8::<PlainRcs>(scope, plain_rcs).unwrap();
+        assert!(Rc::ptr_eq(&plain_rcs.a, &plain_rcs.b));
+        assert_eq!(plain_rcs.a.a, 42);
+    })
+}
+
+#[derive(Facet)]
+struct PlainArcs {
+    items: Vec<Arc<Plain>>,
+}
+
+#[test]
+fn shared_pointers_from_v8() {
+    run(|scope| {
+        let func = compile_function(
+            scope,
+            "make",
+            r#"function make() {
+                const shared = { a: 1, b: 'shared', c: 1.5 };
+                return { items: [shared, { a: 2, b: 'other', c: 2.5 }, shared] };
+            }"#,
+        );
+        let global = scope.get_current_context().global(scope);
+        let value = func.call(scope, global.into(), &[]).unwrap();
+        let plain_arcs = from_v8::<PlainArcs>(scope, value).unwrap();
+        assert_eq!(plain_arcs.items.len(), 3);
+        assert!(Arc::ptr_eq(&plain_arcs.items[0], &plain_arcs.items[2]));
+        assert!(!Arc::ptr_eq(&plain_arcs.items[0], &plain_arcs.items[1]));
+        assert_eq!(plain_arcs.items[1].b, "other");
+
+        // The same object unmarshalled as different types gets one pointer
+        // per type.
+        let value = eval(
+            scope,
+            "(() => {
+                const shared = { a: 1, b: 'shared', c: 1.5 };
+                return { plain: shared, name: shared, again: shared };
+            })()",
+        );
+        let mixed = from_v8::<MixedRcs>(scope, value).unwrap();
+        assert!(Rc::ptr_eq(&mixed.plain, &mixed.again));
+        assert_eq!(mixed.plain.b, "shared");
+        assert_eq!(mixed.name.b, "shared");
+    })
+}
+
+#[derive(Facet)]
+struct PlainName {
+    b: String,
+}
+
+#[derive(Facet)]
+struct MixedRcs {
+    plain: Rc<Plain>,
+    name: Rc<PlainName>,
+    again: Rc<Plain>,
+}
+
+#[derive(Facet)]
+struct Borrowed<'a> {
+    config: &'a Plain,
+    same_config: &'a Plain,
+    items: &'a [i32],
+    #[facet(typed_array)]
+    bytes: &'a [u8],
+}
+
+#[test]
+fn references() {
+    run(|scope| {
+        let config = Plain {
+            a: 1,
+            b: "config".to_string(),
+            c: 0.5,
+        };
+        let items = vec![1, 2, 3];
+        let borrowed = Borrowed {
+            config: &config,
+            same_config: &config,
+            items: &items,
+            bytes: &[4, 5],
+        };
+        let value = to_v8(scope, &borrowed).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(obj) {
+                if (obj.config !== obj.same_config) {
+                    throw new Error('expected references to the same value to be the same object');
+                }
+                if (obj.config.a !== 1 || obj.config.b !== 'config' || obj.config.c !== 0.5) {
+                    throw new Error(`unexpected config: ${JSON.stringify(obj.config)}`);
+                }
+                if (!Array.isArray(obj.items) || obj.items.join() !== '1,2,3') {
+                    throw new Error(`unexpected items: ${obj.items}`);
+                }
+                if (!(obj.bytes instanceof Uint8Array) || obj.bytes.join() !== '4,5') {
+                    throw new Error(`unexpected bytes: ${obj.bytes}`);
+                }
+            }"#,
+        );
+    })
+}
+
+#[derive(Facet, Debug, PartialEq)]
+struct Request<'a> {
+    name: &'a str,
+    tags: &'a [&'a str],
+    config: &'a Plain,
+    same_config: &'a Plain,
+}
+
+#[test]
+fn borrowed_from_arena() {
+    run(|scope| {
+        let func = compile_function(
+            scope,
+            "make",
+            r#"function make() {
+                const config = { a: 1, b: 'config', c: 0.5 };
+                return { name: 'req', tags: ['x', 'y'], config, same_config: config };
+            }"#,
+        );
+        let global = scope.get_current_context().global(scope);
+        let value = func.call(scope, global.into(), &[]).unwrap();
+
+        let arena = Arena::new();
+        let request = from_v8_in::<Request>(&arena, scope, value).unwrap();
+        assert_eq!(request.name, "req");
+        assert_eq!(request.tags, ["x", "y"]);
+        assert_eq!(request.config.b, "config");
+        assert!(std::ptr::eq(request.config, request.same_config));
+        assert_eq!(format!("{request:?}"), format!("{:?}", *request));
+        let request = request.into_inner();
+        assert_eq!(request.tags, ["x", "y"]);
+
+        // Options apply to borrowed types as well.
+        let options = UnmarshalOptions {
+            unknown_fields: UnknownFields::Deny,
+            ..Default::default()
+        };
+        let extra = eval(
+            scope,
+            "({ extra: true, name: 'req', tags: [], config: null, same_config: null })",
+        );
+        let err = from_v8_in_with_options::<Request>(&arena, scope, extra, &options).unwrap_err();
+        assert!(matches!(err.without_path(), Error::UnknownField { .. }));
+
+        // Borrowed types cannot be unmarshalled without an arena.
+        assert!(from_v8::<Request>(scope, value).is_err());
+    })
+}
+
+#[derive(Facet, Default)]
+struct TreeParent {
+    name: String,
+    children: Vec<Rc<TreeChild>>,
+}
+
+#[derive(Facet)]
+struct TreeChild {
+    name: String,
+    parent: Weak<TreeParent>,
+}
+
+#[test]
+fn weak_pointers() {
+    run(|scope| {
+        let root = Rc::new_cyclic(|weak| TreeParent {
+            name: "root".to_string(),
+            children: vec![
+                Rc::new(TreeChild {
+                    name: "first".to_string(),
+                    parent: weak.clone(),
+                }),
+                Rc::new(TreeChild {
+                    name: "second".to_string(),
+                    parent: weak.clone(),
+                }),
+            ],
+        });
+        let value = to_v8(scope, &root).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(root) {
+                if (root.children.length !== 2) {
+                    throw new Error('expected two children');
+                }
+                if (root.children[0].parent !== root || root.children[1].parent !== root) {
+                    throw new Error('expected children to point back to the root');
+                }
+            }"#,
+        );
+
+        let unmarshalled = from_v8::<Rc<TreeParent>>(scope, value).unwrap();
+        assert_eq!(unmarshalled.name, "root");
+        assert_eq!(unmarshalled.children.len(), 2);
+        for child in &unmarshalled.children {
+            let parent = child.parent.upgrade().expect("parent should be alive");
+            assert!(Rc::ptr_eq(&parent, &unmarshalled));
+        }
+        assert_eq!(unmarshalled.children[1].name, "second");
+
+        // Dead weak pointers become `null`.
+        let orphan = TreeChild {
+            name: "orphan".to_string(),
+            parent: Weak::new(),
+        };
+        let value = to_v8(scope, &orphan).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(orphan) {
+                if (orphan.parent !== null) {
+                    throw new Error('expected parent to be null');
+                }
+            }"#,
+        );
+        let orphan = from_v8::<TreeChild>(scope, value).unwrap();
+        assert!(orphan.parent.upgrade().is_none());
     })
 }
 
@@ -465,3 +840,457 @@ fn typed_arrays_f64() {
         assert_eq!(from_v8::<TypedArray<f64>>(scope, v8_array).unwrap(), array);
     })
 }
+
+#[test]
+fn typed_arrays_u64() {
+    run(|scope| {
+        let array = TypedArray {
+            data: vec![1u64, u64::MAX],
+        };
+        let v8_array = to_v8(scope, &array).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[v8_array],
+            r#"function check(array) {
+                if (!(array.data instanceof BigUint64Array)) {
+                    throw new Error(`Expected BigUint64Array, got ${array}`);
+                }
+                if (array.data.length !== 2 || array.data[0] !== 1n || array.data[1] !== 2n ** 64n - 1n) {
+                    throw new Error(`Expected [1n, 2n ** 64n - 1n], got ${array}`);
+                }
+            }"#,
+        );
+        assert_eq!(from_v8::<TypedArray<u64>>(scope, v8_array).unwrap(), array);
+    })
+}
+
+#[test]
+fn typed_arrays_i64() {
+    run(|scope| {
+        let array = TypedArray {
+            data: vec![-1i64, i64::MIN],
+        };
+        let v8_array = to_v8(scope, &array).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[v8_array],
+            r#"function check(array) {
+                if (!(array.data instanceof BigInt64Array)) {
+                    throw new Error(`Expected BigInt64Array, got ${array}`);
+                }
+                if (array.data.length !== 2 || array.data[0] !== -1n || array.data[1] !== -(2n ** 63n)) {
+                    throw new Error(`Expected [-1n, -(2n ** 63n)], got ${array}`);
+                }
+            }"#,
+        );
+        assert_eq!(from_v8::<TypedArray<i64>>(scope, v8_array).unwrap(), array);
+    })
+}
+
+#[derive(Facet, PartialEq, Debug)]
+struct ImageData {
+    #[facet(clamped_array)]
+    data: Vec<u8>,
+    #[facet(float16_array)]
+    alpha: Vec<f32>,
+}
+
+#[test]
+fn typed_arrays_clamped_and_float16() {
+    run(|scope| {
+        let image = ImageData {
+            data: vec![0, 128, 255],
+            alpha: vec![0.0, 0.5, -2.0, 65504.0, 1.0 / 3.0],
+        };
+        let value = to_v8(scope, &image).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(image) {
+                if (!(image.data instanceof Uint8ClampedArray)) {
+                    throw new Error(`Expected Uint8ClampedArray, got ${image.data}`);
+                }
+                if (image.data.join() !== "0,128,255") {
+                    throw new Error(`Expected [0, 128, 255], got ${image.data}`);
+                }
+                if (!(image.alpha instanceof Float16Array)) {
+                    throw new Error(`Expected Float16Array, got ${image.alpha}`);
+                }
+                const expected = [0, 0.5, -2, 65504, Math.f16round(1 / 3)];
+                if (image.alpha.join() !== expected.join()) {
+                    throw new Error(`Expected ${expected}, got ${image.alpha}`);
+                }
+            }"#,
+        );
+        let roundtrip = from_v8::<ImageData>(scope, value).unwrap();
+        assert_eq!(roundtrip.data, image.data);
+        assert_eq!(roundtrip.alpha[..4], image.alpha[..4]);
+        assert!((roundtrip.alpha[4] - 1.0 / 3.0).abs() < 1e-3);
+
+        // Typed arrays created by JS code.
+        let value = eval(scope, "new Uint8ClampedArray([1, 300, -5])");
+        assert_eq!(from_v8::<Vec<u8>>(scope, value).unwrap(), [1, 255, 0]);
+        let value = eval(scope, "new Float16Array([1.5, -0, Infinity, 6e-8])");
+        let floats = from_v8::<Vec<f64>>(scope, value).unwrap();
+        assert_eq!(floats[..3], [1.5, -0.0, f64::INFINITY]);
+        assert_eq!(floats[3], 2f64.powi(-24));
+    })
+}
+
+#[derive(Facet, PartialEq, Debug)]
+struct Frame {
+    #[facet(typed_array)]
+    pixels: Vec<u8>,
+    #[facet(typed_array)]
+    samples: Option<Vec<f32>>,
+    #[facet(typed_array)]
+    empty: Vec<u16>,
+}
+
+#[test]
+fn typed_arrays_owned() {
+    run(|scope| {
+        let frame = Frame {
+            pixels: vec![1, 2, 3],
+            samples: Some(vec![0.5, -0.5]),
+            empty: vec![],
+        };
+        let pixels_ptr = frame.pixels.as_ptr() as *mut std::ffi::c_void;
+        let expected = Frame {
+            pixels: frame.pixels.clone(),
+            samples: frame.samples.clone(),
+            empty: vec![],
+        };
+        let value = to_v8_owned(
+            scope,
+            frame,
+            &MarshalOptions::default(),
+            &mut Constructors::default(),
+        )
+        .unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(frame) {
+                if (!(frame.pixels instanceof Uint8Array) || frame.pixels.join() !== "1,2,3") {
+                    throw new Error(`Expected Uint8Array [1, 2, 3], got ${frame.pixels}`);
+                }
+                if (!(frame.samples instanceof Float32Array) || frame.samples.join() !== "0.5,-0.5") {
+                    throw new Error(`Expected Float32Array [0.5, -0.5], got ${frame.samples}`);
+                }
+                if (!(frame.empty instanceof Uint16Array) || frame.empty.length !== 0) {
+                    throw new Error(`Expected empty Uint16Array, got ${frame.empty}`);
+                }
+            }"#,
+        );
+
+        // The allocation of the `Vec` backs the typed array.
+        let object = v8::Local::<v8::Object>::try_from(value).unwrap();
+        let key = v8::String::new(scope, "pixels").unwrap();
+        let pixels = object.get(scope, key.into()).unwrap();
+        let pixels = v8::Local::<v8::TypedArray>::try_from(pixels).unwrap();
+        let buffer = pixels.buffer(scope).unwrap();
+        assert_eq!(buffer.data().unwrap().as_ptr(), pixels_ptr);
+
+        assert_eq!(from_v8::<Frame>(scope, value).unwrap(), expected);
+    })
+}
+
+#[derive(Facet, Debug)]
+struct Payload {
+    bytes: V8Buffer<u8>,
+    floats: V8Buffer<f32>,
+}
+
+#[test]
+fn v8_buffer() {
+    run(|scope| {
+        let value = eval(
+            scope,
+            r#"globalThis.payload = {
+                bytes: new Uint8Array([1, 2, 3, 4]).subarray(1),
+                floats: new Float32Array([0.5, 1.5]),
+            }"#,
+        );
+        let payload = from_v8::<Payload>(scope, value).unwrap();
+        assert_eq!(payload.bytes.to_vec(), [2, 3, 4]);
+        assert_eq!(payload.bytes.byte_offset(), 1);
+        assert_eq!(payload.floats.to_vec(), [0.5, 1.5]);
+
+        // The memory is shared with JS.
+        eval(scope, "payload.bytes[0] = 9");
+        assert_eq!(payload.bytes.to_vec(), [9, 3, 4]);
+        let mut bytes = [0; 3];
+        payload.bytes.copy_to_slice(&mut bytes);
+        assert_eq!(bytes, [9, 3, 4]);
+        // SAFETY: No JS code runs while the slice is borrowed.
+        assert_eq!(unsafe { payload.bytes.as_slice() }, [9, 3, 4]);
+
+        // Marshalled as a view on the same memory.
+        let value = to_v8(scope, &payload).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(payload) {
+                if (!(payload.bytes instanceof Uint8Array) || payload.bytes.join() !== "9,3,4") {
+                    throw new Error(`Expected Uint8Array [9, 3, 4], got ${payload.bytes}`);
+                }
+                if (!(payload.floats instanceof Float32Array)) {
+                    throw new Error(`Expected Float32Array, got ${payload.floats}`);
+                }
+                payload.floats[1] = 2.5;
+            }"#,
+        );
+        assert_eq!(payload.floats.to_vec(), [0.5, 2.5]);
+
+        // Untyped buffers.
+        let value = eval(scope, "new DataView(new ArrayBuffer(8), 4)");
+        assert_eq!(from_v8::<V8Buffer<f32>>(scope, value).unwrap().to_vec(), [0.0]);
+        let value = eval(scope, "new ArrayBuffer(2)");
+        assert_eq!(from_v8::<V8Buffer<u8>>(scope, value).unwrap().to_vec(), [0, 0]);
+
+        // Mismatched, misaligned and shared buffers.
+        let value = eval(scope, "new Uint32Array(1)");
+        assert!(from_v8::<V8Buffer<f32>>(scope, value).is_err());
+        let value = eval(scope, "new DataView(new ArrayBuffer(9), 1)");
+        assert!(from_v8::<V8Buffer<f32>>(scope, value).is_err());
+        let value = eval(scope, "new Uint8Array(new SharedArrayBuffer(4))");
+        assert!(from_v8::<V8Buffer<u8>>(scope, value).is_err());
+    })
+}
+
+#[test]
+fn typed_arrays_usize() {
+    run(|scope| {
+        let array = TypedArray {
+            data: vec![1usize, 2, 3],
+        };
+        let v8_array = to_v8(scope, &array).unwrap();
+        #[cfg(target_pointer_width = "64")]
+        let expected = "BigUint64Array";
+        #[cfg(target_pointer_width = "32")]
+        let expected = "Uint32Array";
+        let check = format!(
+            r#"function check(array) {{
+                if (!(array.data instanceof {expected})) {{
+                    throw new Error(`Expected {expected}, got ${{array}}`);
+                }}
+            }}"#
+        );
+        check_function(scope, "check", &[v8_array], &check);
+        assert_eq!(from_v8::<TypedArray<usize>>(scope, v8_array).unwrap(), array);
+
+        let array = TypedArray {
+            data: vec![-1isize, 2],
+        };
+        let v8_array = to_v8(scope, &array).unwrap();
+        assert_eq!(from_v8::<TypedArray<isize>>(scope, v8_array).unwrap(), array);
+    })
+}
+
+#[derive(Facet, PartialEq, Debug)]
+struct Sequences {
+    #[facet(typed_array)]
+    fixed: [f32; 3],
+    #[facet(typed_array)]
+    long: [u16; 100],
+    pairs: [[i32; 2]; 2],
+}
+
+#[test]
+fn typed_arrays_sequences() {
+    run(|scope| {
+        let sequences = Sequences {
+            fixed: [0.5, 1.5, 2.5],
+            long: std::array::from_fn(|i| i as u16),
+            pairs: [[-1, 0], [1, 2]],
+        };
+        let value = to_v8(scope, &sequences).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(s) {
+                if (!(s.fixed instanceof Float32Array) || s.fixed.join() !== "0.5,1.5,2.5") {
+                    throw new Error(`Expected Float32Array [0.5, 1.5, 2.5], got ${s.fixed}`);
+                }
+                if (!(s.long instanceof Uint16Array) || s.long.length !== 100 || s.long[99] !== 99) {
+                    throw new Error(`Expected Uint16Array [0, ..., 99], got ${s.long}`);
+                }
+                if (!Array.isArray(s.pairs) || JSON.stringify(s.pairs) !== "[[-1,0],[1,2]]") {
+                    throw new Error(`Expected [[-1, 0], [1, 2]], got ${JSON.stringify(s.pairs)}`);
+                }
+            }"#,
+        );
+        assert_eq!(from_v8::<Sequences>(scope, value).unwrap(), sequences);
+
+        // Fixed-size arrays must have the exact length.
+        let value = eval(scope, "new Float32Array([1, 2])");
+        let err = from_v8::<[f32; 3]>(scope, value).unwrap_err();
+        assert!(matches!(err, Error::LengthMismatch { expected: 3, actual: 2, .. }));
+        let value = eval(scope, "[1, 2, 3, 4]");
+        let err = from_v8::<[f32; 3]>(scope, value).unwrap_err();
+        assert!(matches!(err, Error::LengthMismatch { expected: 3, actual: 4, .. }));
+        let value = eval(scope, "[1, 2, 3]");
+        assert_eq!(from_v8::<[f32; 3]>(scope, value).unwrap(), [1.0, 2.0, 3.0]);
+        let value = eval(scope, "new Float32Array([1, 2, 3])");
+        assert_eq!(from_v8::<[f32; 3]>(scope, value).unwrap(), [1.0, 2.0, 3.0]);
+        let value = eval(scope, "[[1, 2], [3, 4, 5]]");
+        let err = from_v8::<[[u8; 2]; 2]>(scope, value).unwrap_err();
+        assert!(matches!(err.without_path(), Error::LengthMismatch { expected: 2, actual: 3, .. }));
+        // The element type must still match.
+        let value = eval(scope, "new Uint8Array([1, 2, 3])");
+        assert!(from_v8::<[f32; 3]>(scope, value).is_err());
+    })
+}
+
+#[derive(Facet, PartialEq, Debug, Clone, Copy)]
+#[repr(C)]
+struct Vertex {
+    position: [f32; 3],
+    color: [u8; 4],
+}
+
+#[derive(Facet, PartialEq, Debug)]
+struct Mesh {
+    #[facet(flat_array)]
+    normals: Vec<[f32; 3]>,
+    #[facet(flat_array)]
+    vertices: Vec<Vertex>,
+}
+
+#[derive(Facet, PartialEq, Debug)]
+struct BorrowedMesh<'a> {
+    #[facet(flat_array)]
+    triangle: [[f32; 3]; 3],
+    #[facet(flat_array)]
+    vertices: &'a [Vertex],
+    #[facet(flat_array)]
+    same_vertices: &'a [Vertex],
+}
+
+#[derive(Facet, PartialEq, Debug)]
+#[repr(C)]
+struct Padded {
+    a: u8,
+    b: u32,
+}
+
+#[derive(Facet, Debug)]
+struct PaddedList {
+    #[facet(flat_array)]
+    items: Vec<Padded>,
+}
+
+#[test]
+fn flat_arrays() {
+    run(|scope| {
+        let mesh = Mesh {
+            normals: vec![[0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+            vertices: vec![
+                Vertex {
+                    position: [0.5, 1.5, 2.5],
+                    color: [255, 0, 0, 255],
+                },
+                Vertex {
+                    position: [-1.0, 0.0, 1.0],
+                    color: [0, 0, 255, 128],
+                },
+            ],
+        };
+        let value = to_v8(scope, &mesh).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(mesh) {
+                if (!(mesh.normals instanceof Float32Array) || mesh.normals.join() !== "0,1,0,1,0,0") {
+                    throw new Error(`Expected flat Float32Array, got ${mesh.normals}`);
+                }
+                if (!(mesh.vertices instanceof ArrayBuffer) || mesh.vertices.byteLength !== 32) {
+                    throw new Error(`Expected ArrayBuffer of 32 bytes, got ${mesh.vertices}`);
+                }
+                const positions = new Float32Array(mesh.vertices);
+                const colors = new Uint8Array(mesh.vertices);
+                if (positions[4] !== 0 || positions[5] !== 1 || colors[28] !== 0 || colors[31] !== 128) {
+                    throw new Error(`Unexpected vertex data`);
+                }
+            }"#,
+        );
+        assert_eq!(from_v8::<Mesh>(scope, value).unwrap(), mesh);
+
+        // Flat buffers created by JS code.
+        let value = eval(
+            scope,
+            "({ normals: new Float32Array([1, 2, 3, 4, 5, 6]), vertices: new ArrayBuffer(0) })",
+        );
+        assert_eq!(
+            from_v8::<Mesh>(scope, value).unwrap().normals,
+            [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]
+        );
+        let value = eval(
+            scope,
+            "({ normals: new Float32Array([1, 2, 3, 4]), vertices: new ArrayBuffer(0) })",
+        );
+        assert!(from_v8::<Mesh>(scope, value).is_err());
+        let value = eval(scope, "({ normals: new Int32Array(6), vertices: new ArrayBuffer(0) })");
+        assert!(from_v8::<Mesh>(scope, value).is_err());
+
+        // Only fields with the attribute accept flat buffers.
+        let value = eval(scope, "new Float32Array([1, 2, 3, 4, 5, 6])");
+        assert!(from_v8::<Vec<[f32; 3]>>(scope, value).is_err());
+
+        // Structs are only read from typed arrays with `Reinterpret`.
+        let value = eval(scope, "({ normals: [], vertices: new Uint8Array(16) })");
+        assert!(from_v8::<Mesh>(scope, value).is_err());
+        let reinterpret = UnmarshalOptions {
+            typed_arrays: TypedArrayConversion::Reinterpret,
+            ..Default::default()
+        };
+        let mesh = from_v8_with_options::<Mesh>(scope, value, &reinterpret).unwrap();
+        assert_eq!(mesh.vertices.len(), 1);
+
+        // Fixed-size arrays, and references to slices, with the identity of
+        // shared ones preserved.
+        let borrowed = BorrowedMesh {
+            triangle: [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            vertices: &mesh.vertices,
+            same_vertices: &mesh.vertices,
+        };
+        let value = to_v8(scope, &borrowed).unwrap();
+        check_function(
+            scope,
+            "check",
+            &[value],
+            r#"function check(mesh) {
+                if (!(mesh.triangle instanceof Float32Array) || mesh.triangle.join() !== "0,1,0,1,0,0,0,0,1") {
+                    throw new Error(`Expected flat Float32Array, got ${mesh.triangle}`);
+                }
+                if (!(mesh.vertices instanceof ArrayBuffer) || mesh.vertices !== mesh.same_vertices) {
+                    throw new Error(`Expected one shared ArrayBuffer, got ${mesh.vertices}`);
+                }
+            }"#,
+        );
+        let arena = Arena::new();
+        let round_trip = from_v8_in::<BorrowedMesh>(&arena, scope, value).unwrap();
+        assert_eq!(*round_trip, borrowed);
+        assert!(std::ptr::eq(round_trip.vertices, round_trip.same_vertices));
+        let value = eval(
+            scope,
+            "({ triangle: new Float32Array(6), vertices: [], same_vertices: [] })",
+        );
+        let err = from_v8_in::<BorrowedMesh>(&arena, scope, value).unwrap_err();
+        assert!(matches!(err.without_path(), Error::LengthMismatch { expected: 3, actual: 2, .. }));
+
+        // Structs with padding are rejected.
+        let list = PaddedList {
+            items: vec![Padded { a: 1, b: 2 }],
+        };
+        assert!(to_v8(scope, &list).is_err());
+    })
+}
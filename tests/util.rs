@@ -40,6 +40,19 @@ pub fn compile_function<'s>(
     func
 }
 
+#[allow(dead_code)]
+pub fn eval<'s>(scope: &mut v8::HandleScope<'s>, script: &str) -> v8::Local<'s, v8::Value> {
+    let mut scope = v8::TryCatch::new(scope);
+    let source = v8::String::new(&mut scope, script).expect("script too large");
+    let Some(script) = v8::Script::compile(&mut scope, source, None) else {
+        fail_on_exception(&mut scope);
+    };
+    let Some(value) = script.run(&mut scope) else {
+        fail_on_exception(&mut scope);
+    };
+    value
+}
+
 #[allow(dead_code)]
 pub fn check_function<'s>(
     scope: &mut v8::HandleScope<'s>,